@@ -0,0 +1,165 @@
+//! Rough timings and allocation counts of the bridge, run with `cargo bench`, and with
+//! `cargo bench --features crossbeam` to compare the two channel backends.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use rayon_par_bridge::{
+    par_bridge, par_bridge_adaptive, par_bridge_collect, par_bridge_copy, par_bridge_count,
+    par_bridge_in, par_bridge_rendezvous, BridgePool,
+};
+
+const RUNS: u32 = 10;
+
+/// The channel the items go through, picked by the `crossbeam` feature.
+const BACKEND: &str = if cfg!(feature = "crossbeam") { "crossbeam" } else { "mpsc" };
+
+/// Counts the allocations of the whole process.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Prints the average time it takes to run `f` and the number of allocations it does.
+fn bench(name: &str, mut f: impl FnMut()) {
+    f(); // warm-up
+    let mut total = Duration::ZERO;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / RUNS as usize;
+    println!("{name:<40} {:>10.2?} {allocations:>10} allocs", total / RUNS);
+}
+
+/// One million items sent by 16 workers all contending on the same channel.
+fn contention() {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(16).build().unwrap();
+    bench(&format!("contention/1M items/16 workers/{BACKEND}"), || {
+        let parallel_pipeline = (0u64..1_000_000).into_par_iter();
+        let sum = par_bridge_in(&pool, 64, parallel_pipeline, |seq_iter| seq_iter.sum::<u64>());
+        black_box(sum);
+    });
+}
+
+/// Collecting one million items, with and without preallocating.
+fn collect() {
+    let data = (0u64..1_000_000).collect::<Vec<_>>();
+    bench("collect/1M items/generic", || {
+        let result: Vec<_> = par_bridge(64, data.par_iter(), |seq_iter| seq_iter.collect());
+        black_box(result);
+    });
+    bench("collect/1M items/par_bridge_collect", || {
+        let result: Vec<_> = par_bridge_collect(64, data.par_iter());
+        black_box(result);
+    });
+}
+
+/// Ten thousand bridges of ten items, spawning a thread each time or reusing one.
+fn tiny_bridges() {
+    bench("tiny bridges/10k bridges/scoped", || {
+        for _ in 0..10_000 {
+            black_box(par_bridge(4, (0u32..10).into_par_iter(), |seq_iter| seq_iter.sum::<u32>()));
+        }
+    });
+    let mut pool = BridgePool::new();
+    bench("tiny bridges/10k bridges/pooled", || {
+        for _ in 0..10_000 {
+            black_box(pool.bridge(4, (0u32..10).into_par_iter(), |seq_iter| seq_iter.sum::<u32>()));
+        }
+    });
+}
+
+/// Ten million small items, sent one by one or in arrays.
+fn small_items() {
+    bench("small items/10M u32/par_bridge", || {
+        let sum = par_bridge(64, (0u32..10_000_000).into_par_iter(), |seq_iter| {
+            seq_iter.fold(0u64, |sum, num| sum + num as u64)
+        });
+        black_box(sum);
+    });
+    bench("small items/10M u32/par_bridge_copy", || {
+        let sum = par_bridge_copy(64, (0u32..10_000_000).into_par_iter(), |seq_iter| {
+            seq_iter.fold(0u64, |sum, num| sum + num as u64)
+        });
+        black_box(sum);
+    });
+}
+
+/// Counting a hundred thousand items of 4 KiB, through the channel or on the workers.
+fn count() {
+    let pipeline = || (0u32..100_000).into_par_iter().map(|_| vec![0u8; 4096]);
+    bench("count/100k 4KiB items/par_bridge", || {
+        black_box(par_bridge(64, pipeline(), Iterator::count));
+    });
+    bench("count/100k 4KiB items/par_bridge_count", || {
+        black_box(par_bridge_count(pipeline()));
+    });
+}
+
+/// A producer pausing every thousand items, bridged with a small fixed bound or an adaptive one.
+fn bursty() {
+    let pipeline = || {
+        (0u64..100_000).into_par_iter().inspect(|num| {
+            if num % 1000 == 0 {
+                std::thread::sleep(Duration::from_micros(200));
+            }
+        })
+    };
+    let consume = |seq_iter: &mut dyn Iterator<Item = u64>| {
+        seq_iter.fold(0u64, |sum, num| black_box(sum ^ num))
+    };
+    bench("bursty/100k items/bound 4", || {
+        black_box(par_bridge(4, pipeline(), |mut seq_iter| consume(&mut seq_iter)));
+    });
+    bench("bursty/100k items/adaptive 4..=1024", || {
+        black_box(par_bridge_adaptive(4, 1024, pipeline(), |mut seq_iter| consume(&mut seq_iter)));
+    });
+}
+
+/// A hundred thousand items handed over one at a time, through a channel of one item or a single slot.
+fn rendezvous() {
+    bench("rendezvous/100k items/bound 1", || {
+        let sum = par_bridge(1, (0u64..100_000).into_par_iter(), |seq_iter| seq_iter.sum::<u64>());
+        black_box(sum);
+    });
+    bench("rendezvous/100k items/par_bridge_rendezvous", || {
+        let sum = par_bridge_rendezvous((0u64..100_000).into_par_iter(), |seq_iter| {
+            seq_iter.sum::<u64>()
+        });
+        black_box(sum);
+    });
+}
+
+fn main() {
+    contention();
+    collect();
+    tiny_bridges();
+    small_items();
+    count();
+    bursty();
+    rendezvous();
+}
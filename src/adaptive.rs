@@ -0,0 +1,257 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, IntoIter, TryRecvError};
+use crate::{bridge_with, RayonIntoIter};
+
+/// The number of consecutive starvations, or blocked sends, after which the capacity is resized.
+const RESIZE_AFTER: usize = 4;
+
+/// Same as [`par_bridge`](crate::par_bridge) but adapts the number of items the channel can
+/// hold, between `min_bound` and `max_bound`, to the relative speed of both sides.
+///
+/// The channel starts with room for `min_bound` items. When the consumer repeatedly has to
+/// wait for an item, its capacity doubles so that a bursty parallel pipeline can get further
+/// ahead. When the Rayon workers repeatedly wait for room, the consumer is the bottleneck and
+/// the capacity is halved, which saves the memory of items that would only wait longer.
+///
+/// # Panics
+///
+/// Panics if `min_bound` is zero or greater than `max_bound`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_adaptive;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let sum = par_bridge_adaptive(2, 64, data.into_par_iter(), |seq_iter| seq_iter.sum::<u32>());
+///
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_adaptive<I, F, R>(min_bound: usize, max_bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    assert!(min_bound != 0, "the minimum bound must not be zero");
+    assert!(min_bound <= max_bound, "the minimum bound must not exceed the maximum bound");
+    bridge_adaptive(Arc::new(Capacity::new(min_bound, max_bound)), iter, f)
+}
+
+fn bridge_adaptive<I, F, R>(capacity: Arc<Capacity>, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let producer_capacity = capacity.clone();
+    bridge_with(
+        thread::Builder::new(),
+        channel::unbounded(),
+        move |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                producer_capacity.acquire()?;
+                send.send(x).ok()
+            });
+        },
+        |recv| f(RayonIntoIter::adaptive(Adaptive { recv, capacity })),
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+/// Yields the items received, gives their slot back and reports the starvations.
+pub(crate) struct Adaptive<T> {
+    recv: IntoIter<T>,
+    capacity: Arc<Capacity>,
+}
+
+impl<T> Adaptive<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        let x = match self.recv.recv(false) {
+            // Only a consumer about to wait starves, polling an empty channel is not a sign that
+            // more room would help.
+            Err(TryRecvError::Empty) if block => {
+                self.capacity.starved();
+                self.recv.recv(block)?
+            }
+            item => item?,
+        };
+        self.capacity.release();
+        Ok(x)
+    }
+}
+
+impl<T> Drop for Adaptive<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        self.capacity.close();
+    }
+}
+
+/// The number of items that can be in flight at the same time, resized as the bridge runs.
+struct Capacity {
+    min: usize,
+    max: usize,
+    state: Mutex<CapacityState>,
+    /// Notified when an item is consumed, the capacity grows or the consumer is dropped.
+    cond: Condvar,
+}
+
+struct CapacityState {
+    in_flight: usize,
+    current: usize,
+    /// The consecutive receives that found the channel empty.
+    starved: usize,
+    /// The consecutive sends that had to wait for room.
+    blocked: usize,
+    closed: bool,
+}
+
+impl Capacity {
+    fn new(min: usize, max: usize) -> Capacity {
+        Capacity {
+            min,
+            max,
+            state: Mutex::new(CapacityState {
+                in_flight: 0,
+                current: min,
+                starved: 0,
+                blocked: 0,
+                closed: false,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Blocks until there is room for an item, returns `None` if the consumer was dropped.
+    fn acquire(&self) -> Option<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.in_flight >= state.current && !state.closed {
+            state.starved = 0;
+            state.blocked += 1;
+            if state.blocked == RESIZE_AFTER {
+                state.blocked = 0;
+                state.current = (state.current / 2).max(self.min);
+            }
+        }
+        while state.in_flight >= state.current && !state.closed {
+            state = self.cond.wait(state).unwrap();
+        }
+
+        if state.closed {
+            return None;
+        }
+
+        state.in_flight += 1;
+        Some(())
+    }
+
+    fn release(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.cond.notify_one();
+    }
+
+    fn starved(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.blocked = 0;
+        state.starved += 1;
+        if state.starved == RESIZE_AFTER {
+            state.starved = 0;
+            state.current = (state.current * 2).min(self.max);
+            self.cond.notify_all();
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.cond.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn slow_producer_grows_the_capacity() {
+        const MAX_BOUND: usize = 32;
+
+        let capacity = Arc::new(Capacity::new(2, MAX_BOUND));
+        // A single worker computes the items, so that none of them waits for room however
+        // the threads are scheduled, and the consumer starves for each of them.
+        let parallel_pipeline = (0u32..200)
+            .into_par_iter()
+            .with_min_len(200)
+            .inspect(|_| thread::sleep(Duration::from_micros(200)));
+
+        let (count, peak_capacity, peak_in_flight) =
+            bridge_adaptive(capacity.clone(), parallel_pipeline, |seq_iter| {
+                let (mut peak_capacity, mut peak_in_flight) = (0, 0);
+                let count = seq_iter
+                    .inspect(|_| {
+                        let state = capacity.state.lock().unwrap();
+                        peak_capacity = peak_capacity.max(state.current);
+                        peak_in_flight = peak_in_flight.max(state.in_flight);
+                    })
+                    .count();
+                (count, peak_capacity, peak_in_flight)
+            });
+
+        assert_eq!(count, 200);
+        assert!(peak_capacity > 2, "the capacity never grew");
+        assert!(peak_capacity <= MAX_BOUND, "the capacity reached {peak_capacity}");
+        assert!(peak_in_flight <= MAX_BOUND, "{peak_in_flight} items were in flight");
+    }
+
+    #[test]
+    fn polling_an_idle_bridge_keeps_the_capacity() {
+        let capacity = Arc::new(Capacity::new(2, 32));
+        let go = AtomicBool::new(false);
+        let parallel_pipeline = (0u32..4).into_par_iter().inspect(|_| {
+            while !go.load(Ordering::Relaxed) {
+                thread::yield_now();
+            }
+        });
+
+        let (polled, current, count) =
+            bridge_adaptive(capacity.clone(), parallel_pipeline, |mut seq_iter| {
+                let mut polled = 0;
+                for _ in 0..RESIZE_AFTER * 10 {
+                    polled += seq_iter.drain_available(8).len();
+                    polled += usize::from(seq_iter.try_next().is_ok());
+                }
+                let current = capacity.state.lock().unwrap().current;
+                go.store(true, Ordering::Relaxed);
+                (polled, current, seq_iter.count())
+            });
+
+        assert_eq!(polled, 0);
+        assert_eq!(current, 2);
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn slow_consumer_shrinks_the_capacity() {
+        let capacity = Arc::new(Capacity::new(2, 32));
+        capacity.state.lock().unwrap().current = 32;
+
+        let count = bridge_adaptive(capacity.clone(), (0u32..200).into_par_iter(), |seq_iter| {
+            seq_iter.inspect(|_| thread::sleep(Duration::from_micros(200))).count()
+        });
+
+        assert_eq!(count, 200);
+        assert_eq!(capacity.state.lock().unwrap().current, 2);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{array, iter, mem, thread};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{IntoIter, TryRecvError};
+use crate::{bridge_with, channel, RayonIntoIter};
+
+/// The number of items sent at once by [`par_bridge_copy`].
+const CHUNK_LEN: usize = 64;
+
+/// Same as [`par_bridge`](crate::par_bridge) but sends the items through the channel in
+/// batches of up to `batch_size` items, reducing the per-item channel overhead.
+///
+/// Each Rayon work unit accumulates the items it produces and sends them once the batch is
+/// full, a partially filled batch is sent when the work unit ends. The consumer still sees the
+/// items one by one. The `bound` parameter counts batches, not items.
+///
+/// # Panics
+///
+/// Panics if `batch_size` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_batched;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let result: Vec<_> = par_bridge_batched(5, 16, parallel_pipeline, |seq_iter| seq_iter.collect());
+/// assert_eq!(result.len(), 100);
+/// ```
+pub fn par_bridge_batched<I, F, R>(bound: usize, batch_size: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    assert!(batch_size != 0, "batch size must be non-zero");
+    bridge_with(
+        thread::Builder::new(),
+        channel::bounded(bound),
+        |send| {
+            iter.into_par_iter()
+                .try_fold(
+                    || Vec::with_capacity(batch_size),
+                    |mut batch, x| {
+                        batch.push(x);
+                        if batch.len() == batch_size {
+                            send.send(mem::replace(&mut batch, Vec::with_capacity(batch_size)))
+                                .ok()?;
+                        }
+                        Some(batch)
+                    },
+                )
+                // Flush what is left at the end of each work unit.
+                .try_for_each(|batch| match batch? {
+                    batch if batch.is_empty() => Some(()),
+                    batch => send.send(batch).ok(),
+                });
+        },
+        |recv| f(RayonIntoIter::batched(recv)),
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+/// Same as [`par_bridge_batched`] but for small `Copy` items, which are sent through the
+/// channel in fixed-size arrays of 64 items.
+///
+/// Unlike the batches of [`par_bridge_batched`], the arrays do not need an allocation each,
+/// they are moved through the channel as they are. This works best for items of a few bytes,
+/// like integers, for which the channel operations dominate the cost of the bridge. The
+/// `bound` parameter counts arrays, not items.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_copy;
+/// use rayon::prelude::*;
+///
+/// let sum = par_bridge_copy(5, (0u64..1000).into_par_iter(), |seq_iter| seq_iter.sum::<u64>());
+/// assert_eq!(sum, 499_500);
+/// ```
+pub fn par_bridge_copy<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Item: Copy,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge_with(
+        thread::Builder::new(),
+        channel::bounded(bound),
+        |send| {
+            iter.into_par_iter()
+                .try_fold(
+                    || None,
+                    |chunk: Option<Chunk<I::Item>>, x| {
+                        let chunk = match chunk {
+                            Some(mut chunk) => {
+                                chunk.items[chunk.len] = x;
+                                chunk.len += 1;
+                                chunk
+                            }
+                            // There is no value to fill the array with before the first item.
+                            None => Chunk { items: [x; CHUNK_LEN], len: 1 },
+                        };
+                        if chunk.len == CHUNK_LEN {
+                            send.send(chunk).ok()?;
+                            return Some(None);
+                        }
+                        Some(Some(chunk))
+                    },
+                )
+                // Flush what is left at the end of each work unit.
+                .try_for_each(|chunk| match chunk? {
+                    Some(chunk) => send.send(chunk).ok(),
+                    None => Some(()),
+                });
+        },
+        |recv| f(RayonIntoIter::unpack(Unpack { recv, current: None })),
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+/// The items sent at once by [`par_bridge_copy`], only the first `len` are valid.
+pub(crate) struct Chunk<T> {
+    items: [T; CHUNK_LEN],
+    len: usize,
+}
+
+/// Yields the valid items of the chunks received.
+pub(crate) struct Unpack<T> {
+    recv: IntoIter<Chunk<T>>,
+    current: Option<iter::Take<array::IntoIter<T, CHUNK_LEN>>>,
+}
+
+impl<T> Unpack<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        loop {
+            if let Some(item) = self.current.as_mut().and_then(Iterator::next) {
+                return Ok(item);
+            }
+            let chunk = self.recv.recv(block)?;
+            self.current = Some(chunk.items.into_iter().take(chunk.len));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn all_items_exactly_once() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        for batch_size in [1, 7, 1024] {
+            let parallel_pipeline = data.par_iter().copied().filter(|num| num % 3 != 0);
+            let mut result: Vec<_> =
+                par_bridge_batched(4, batch_size, parallel_pipeline, |seq_iter| seq_iter.collect());
+
+            result.sort_unstable();
+            let expected: Vec<_> = data.iter().copied().filter(|num| num % 3 != 0).collect();
+            assert_eq!(result, expected, "batch_size = {batch_size}");
+        }
+    }
+
+    #[test]
+    fn copy_delivers_every_item() {
+        let count = CHUNK_LEN * 100 + 17;
+        let mut result: Vec<_> =
+            par_bridge_copy(4, (0..count).into_par_iter(), |seq_iter| seq_iter.collect());
+
+        result.sort_unstable();
+        assert_eq!(result, (0..count).collect::<Vec<_>>());
+    }
+}
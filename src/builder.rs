@@ -0,0 +1,352 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::{fmt, thread};
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator};
+
+use crate::ordered::bridge_ordered;
+use crate::{auto_bound, par_bridge_with_builder, RayonIntoIter};
+
+/// A reusable configuration for bridging parallel pipelines.
+///
+/// The defaults reproduce the behavior of [`par_bridge`](crate::par_bridge), with a channel
+/// holding a few items per Rayon worker, as chosen by [`auto_bound`](crate::auto_bound).
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::ParBridge;
+/// use rayon::prelude::*;
+///
+/// let bridge = ParBridge::new().bound(5).ordered(true).thread_name("producer");
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.par_iter().map(|num| num * 2);
+/// let result: Vec<_> = bridge.clone().run_indexed(parallel_pipeline, |seq_iter| seq_iter.collect());
+///
+/// assert_eq!(result, (0u32..100).map(|num| num * 2).collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParBridge {
+    bound: Option<usize>,
+    ordered: bool,
+    thread_name: Option<String>,
+    on_panic: PanicPolicy,
+}
+
+/// What a [`ParBridge`] does when the parallel pipeline panics, see [`ParBridge::on_panic`].
+///
+/// The consumer sees the stream end early in every case, and a panic of the consumer itself is
+/// always resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// Resume the panic on the calling thread once the consumer returns.
+    #[default]
+    Propagate,
+    /// Discard the panic and return the result of the consumer, computed from the items
+    /// received before the panic.
+    Ignore,
+    /// Return the panic as a [`BridgePanic`] error, only available with [`ParBridge::try_run`]
+    /// and [`ParBridge::try_run_indexed`].
+    AsError,
+}
+
+/// The panic of a parallel pipeline, returned by the bridges configured with
+/// [`PanicPolicy::AsError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BridgePanic {
+    /// The panic message, when the payload is a string like the ones of `panic!`, with the same
+    /// context as the panics resumed by [`par_bridge`](crate::par_bridge).
+    pub payload_msg: String,
+}
+
+impl BridgePanic {
+    pub(crate) fn from_payload(payload: Box<dyn Any + Send>) -> BridgePanic {
+        let payload_msg = match payload.downcast::<String>() {
+            Ok(msg) => *msg,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(msg) => msg.to_string(),
+                Err(_) => String::from("Box<dyn Any>"),
+            },
+        };
+        BridgePanic { payload_msg }
+    }
+}
+
+impl fmt::Display for BridgePanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the parallel pipeline panicked: {}", self.payload_msg)
+    }
+}
+
+impl std::error::Error for BridgePanic {}
+
+impl ParBridge {
+    /// Creates a new configuration with the default settings.
+    pub fn new() -> ParBridge {
+        ParBridge::default()
+    }
+
+    /// Sets the size of the channel between the parallel pipeline and the consumer.
+    ///
+    /// Defaults to [`auto_bound`](crate::auto_bound), computed when the bridge is run.
+    pub fn bound(self, bound: usize) -> ParBridge {
+        ParBridge { bound: Some(bound), ..self }
+    }
+
+    /// Whether to yield the items in their original order, see
+    /// [`par_bridge_ordered`](crate::par_bridge_ordered).
+    ///
+    /// Only indexed parallel iterators know the position of their items, ordered
+    /// bridges must be run with [`ParBridge::run_indexed`].
+    ///
+    /// # Panics
+    ///
+    /// An ordered bridge panics when run with [`ParBridge::run`] or [`ParBridge::try_run`],
+    /// which accept any parallel iterator.
+    pub fn ordered(self, ordered: bool) -> ParBridge {
+        ParBridge { ordered, ..self }
+    }
+
+    /// Sets the name of the thread driving the parallel pipeline.
+    pub fn thread_name(self, name: impl Into<String>) -> ParBridge {
+        ParBridge { thread_name: Some(name.into()), ..self }
+    }
+
+    /// Sets what happens when the parallel pipeline panics.
+    ///
+    /// Defaults to [`PanicPolicy::Propagate`].
+    pub fn on_panic(self, on_panic: PanicPolicy) -> ParBridge {
+        ParBridge { on_panic, ..self }
+    }
+
+    /// Consumes the parallel pipeline sequentially with this configuration,
+    /// see [`par_bridge`](crate::par_bridge).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bridge is configured as ordered, use [`ParBridge::run_indexed`] instead,
+    /// or if it returns panics as errors, use [`ParBridge::try_run`] instead.
+    pub fn run<I, F, R>(self, iter: I, f: F) -> R
+    where
+        I: IntoParallelIterator + Send,
+        F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    {
+        assert!(self.on_panic != PanicPolicy::AsError, "use `ParBridge::try_run` with `AsError`");
+        self.try_run(iter, f).unwrap_or_else(|_| unreachable!("the panic was not an error"))
+    }
+
+    /// Same as [`ParBridge::run`] but for indexed parallel iterators, which can also be
+    /// bridged in their original order.
+    pub fn run_indexed<I, F, R>(self, iter: I, f: F) -> R
+    where
+        I: IntoParallelIterator + Send,
+        I::Iter: IndexedParallelIterator,
+        F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    {
+        assert!(
+            self.on_panic != PanicPolicy::AsError,
+            "use `ParBridge::try_run_indexed` with `AsError`"
+        );
+        self.try_run_indexed(iter, f).unwrap_or_else(|_| unreachable!("the panic was not an error"))
+    }
+
+    /// Same as [`ParBridge::run`] but a panic of the parallel pipeline is returned as an
+    /// error when the bridge is configured with [`PanicPolicy::AsError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_par_bridge::{PanicPolicy, ParBridge};
+    /// use rayon::prelude::*;
+    ///
+    /// let bridge = ParBridge::new().on_panic(PanicPolicy::AsError);
+    /// let parallel_pipeline = (0u32..100).into_par_iter().map(|num| match num {
+    ///     42 => panic!("corrupted record"),
+    ///     num => num,
+    /// });
+    ///
+    /// let error = bridge.try_run(parallel_pipeline, Iterator::count).unwrap_err();
+    /// assert!(error.payload_msg.ends_with("corrupted record"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bridge is configured as ordered, use [`ParBridge::try_run_indexed`]
+    /// instead.
+    pub fn try_run<I, F, R>(self, iter: I, f: F) -> Result<R, BridgePanic>
+    where
+        I: IntoParallelIterator + Send,
+        F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    {
+        assert!(!self.ordered, "ordered bridges must be run with `ParBridge::run_indexed`");
+        let (builder, bound) = (self.thread_builder(), self.bound_or_default());
+        catching(self.on_panic, f, |consume| {
+            par_bridge_with_builder(builder, bound, iter, consume)
+                .expect("failed to spawn the producer thread")
+        })
+    }
+
+    /// Same as [`ParBridge::try_run`] but for indexed parallel iterators, which can also be
+    /// bridged in their original order.
+    pub fn try_run_indexed<I, F, R>(self, iter: I, f: F) -> Result<R, BridgePanic>
+    where
+        I: IntoParallelIterator + Send,
+        I::Iter: IndexedParallelIterator,
+        F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    {
+        if !self.ordered {
+            return self.try_run(iter, f);
+        }
+
+        let (builder, bound) = (self.thread_builder(), self.bound_or_default());
+        catching(self.on_panic, f, |consume| {
+            bridge_ordered(builder, bound, iter, |reorder| consume(RayonIntoIter::ordered(reorder)))
+                .expect("failed to spawn the producer thread")
+        })
+    }
+
+    fn bound_or_default(&self) -> usize {
+        self.bound.unwrap_or_else(auto_bound)
+    }
+
+    fn thread_builder(&self) -> thread::Builder {
+        match &self.thread_name {
+            Some(name) => thread::Builder::new().name(name.clone()),
+            None => thread::Builder::new(),
+        }
+    }
+}
+
+/// Runs `bridge` with a consumer calling `f`, and applies `on_panic` to the panic of the
+/// parallel pipeline only.
+fn catching<T, F, R, B>(on_panic: PanicPolicy, f: F, bridge: B) -> Result<R, BridgePanic>
+where
+    F: FnOnce(RayonIntoIter<T>) -> R,
+    B: FnOnce(&mut dyn FnMut(RayonIntoIter<T>)),
+{
+    let mut f = Some(f);
+    let mut result = None;
+    let mut consumer_panic = None;
+    // The consumer panic is caught separately, the one escaping the bridge is the producer's.
+    let produced = panic::catch_unwind(AssertUnwindSafe(|| {
+        bridge(&mut |seq_iter| {
+            let f = f.take().expect("the consumer is called once");
+            match panic::catch_unwind(AssertUnwindSafe(|| f(seq_iter))) {
+                Ok(r) => result = Some(r),
+                Err(payload) => consumer_panic = Some(payload),
+            }
+        })
+    }));
+
+    if let Some(payload) = consumer_panic {
+        panic::resume_unwind(payload);
+    }
+    match produced {
+        Ok(()) => Ok(result.expect("the consumer returned")),
+        Err(payload) => match (on_panic, result) {
+            (PanicPolicy::Ignore, Some(result)) => Ok(result),
+            (PanicPolicy::AsError, _) => Err(BridgePanic::from_payload(payload)),
+            _ => panic::resume_unwind(payload),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn reused_configuration() {
+        let bridge = ParBridge::new().bound(3).ordered(true);
+        let data = (0u32..1000).collect::<Vec<_>>();
+
+        let first: Vec<_> = bridge.clone().run_indexed(data.par_iter().copied(), Iterator::collect);
+        let second: Vec<_> = bridge.run_indexed(data.par_iter().copied(), Iterator::collect);
+
+        assert_eq!(first, data);
+        assert_eq!(second, data);
+    }
+
+    #[test]
+    fn defaults() {
+        let bridge = ParBridge::new();
+        assert_eq!(bridge.bound_or_default(), auto_bound());
+        assert!(!bridge.ordered);
+        assert!(bridge.thread_name.is_none());
+        assert_eq!(bridge.on_panic, PanicPolicy::Propagate);
+
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let mut result: Vec<_> = bridge.run(data.par_iter().copied(), Iterator::collect);
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    #[should_panic(expected = "ordered bridges must be run with `ParBridge::run_indexed`")]
+    fn ordered_run_panics() {
+        ParBridge::new().ordered(true).run((0u32..1000).into_par_iter(), Iterator::count);
+    }
+
+    /// Panics on the item 500, once the items before it have been sent.
+    fn panicking_pipeline() -> impl IndexedParallelIterator<Item = u32> {
+        (0u32..1000).into_par_iter().with_min_len(usize::MAX).inspect(|&num| {
+            if num == 500 {
+                panic!("item {num} failed");
+            }
+        })
+    }
+
+    #[test]
+    #[should_panic(expected = "item 500 failed")]
+    fn propagate_policy() {
+        ParBridge::new()
+            .on_panic(PanicPolicy::Propagate)
+            .run(panicking_pipeline(), Iterator::count);
+    }
+
+    #[test]
+    fn ignore_policy() {
+        let count = ParBridge::new()
+            .on_panic(PanicPolicy::Ignore)
+            .run(panicking_pipeline(), Iterator::count);
+        assert_eq!(count, 500);
+    }
+
+    #[test]
+    fn as_error_policy() {
+        let bridge =
+            ParBridge::new().bound(5).thread_name("decoder").on_panic(PanicPolicy::AsError);
+        let error = bridge.try_run(panicking_pipeline(), Iterator::count).unwrap_err();
+
+        let message = "par_bridge producer panicked (bound=5, thread=decoder): item 500 failed";
+        assert_eq!(error.payload_msg, message);
+        assert_eq!(error.to_string(), format!("the parallel pipeline panicked: {message}"));
+    }
+
+    #[test]
+    fn thread_name_in_the_panic_message() {
+        for ordered in [false, true] {
+            let bridge = ParBridge::new().bound(5).thread_name("decoder").ordered(ordered);
+            let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+                bridge.run_indexed(panicking_pipeline(), Iterator::count)
+            }))
+            .unwrap_err();
+
+            let message = payload.downcast::<String>().unwrap();
+            assert_eq!(
+                *message,
+                "par_bridge producer panicked (bound=5, thread=decoder): item 500 failed"
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "consumer failed")]
+    fn consumer_panics_are_always_resumed() {
+        let bridge = ParBridge::new().on_panic(PanicPolicy::AsError);
+        let _ = bridge.try_run((0u32..1000).into_par_iter(), |_| panic!("consumer failed"));
+    }
+}
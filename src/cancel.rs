@@ -0,0 +1,403 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but stops the parallel pipeline
+/// as soon as `cancel` is set.
+///
+/// The flag is checked before sending each item, once it is set no more items are sent and
+/// the consumer sees the end of the stream after the items already in flight.
+///
+/// # Workers blocked on a full channel
+///
+/// Setting the flag does not wake up the Rayon workers waiting for room in the channel, they
+/// are released as the consumer keeps pulling items, or when it drops the iterator. Either way
+/// there is no deadlock, but the consumer can see up to `bound` items plus one per Rayon
+/// worker after the flag is set.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use rayon_par_bridge::par_bridge_cancellable;
+/// use rayon::prelude::*;
+///
+/// let cancel = AtomicBool::new(false);
+/// let data = (0u32..10_000).collect::<Vec<_>>();
+///
+/// let count = par_bridge_cancellable(5, data.into_par_iter(), &cancel, |seq_iter| {
+///     seq_iter.inspect(|_| cancel.store(true, Ordering::Relaxed)).count()
+/// });
+///
+/// assert!(count < 10_000);
+/// ```
+pub fn par_bridge_cancellable<I, F, R>(bound: usize, iter: I, cancel: &AtomicBool, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                if cancel.load(Ordering::Relaxed) {
+                    None
+                } else {
+                    send.send(x).ok()
+                }
+            });
+        },
+        f,
+    )
+}
+
+/// Same as [`par_bridge_cancellable`] but, when `f` returns after `cancel` was set, passes the
+/// items still in flight to `on_cancel_drain` instead of dropping them.
+///
+/// This lets items holding resources, like file handles or locks, be released in an orderly
+/// way on the current thread. `on_cancel_drain` receives the items buffered in the channel
+/// along with the ones the Rayon workers blocked on a full channel were about to send, and
+/// nothing produced after the flag was set. Like [`par_bridge_drain`](crate::par_bridge_drain),
+/// `f` borrows the iterator so that the remaining items can be drained once it returns. When
+/// `f` returns without the flag set, the remaining items are dropped as usual.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use rayon_par_bridge::par_bridge_cancellable_drain;
+/// use rayon::prelude::*;
+///
+/// let cancel = AtomicBool::new(false);
+/// let data = (0u32..10_000).collect::<Vec<_>>();
+/// let mut released = 0;
+///
+/// let first = par_bridge_cancellable_drain(
+///     5,
+///     data.into_par_iter(),
+///     &cancel,
+///     |_num| released += 1,
+///     |seq_iter| {
+///         let first = seq_iter.next();
+///         cancel.store(true, Ordering::Relaxed);
+///         first
+///     },
+/// );
+///
+/// assert!(first.is_some());
+/// assert!(released < 10_000);
+/// ```
+pub fn par_bridge_cancellable_drain<I, D, F, R>(
+    bound: usize,
+    iter: I,
+    cancel: &AtomicBool,
+    on_cancel_drain: D,
+    f: F,
+) -> R
+where
+    I: IntoParallelIterator + Send,
+    D: FnMut(I::Item),
+    F: FnOnce(&mut RayonIntoIter<I::Item>) -> R,
+{
+    par_bridge_cancellable(bound, iter, cancel, |mut seq_iter| {
+        let result = f(&mut seq_iter);
+        if cancel.load(Ordering::Relaxed) {
+            seq_iter.for_each(on_cancel_drain);
+        }
+        result
+    })
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but suspends the parallel pipeline while `pause`
+/// is paused.
+///
+/// The Rayon workers check the handle before sending each item and wait while it is paused,
+/// so the pipeline stalls after at most one item per worker and continues where it left off
+/// once resumed, without losing any item. The handle can be cloned and moved to the thread
+/// that decides, like the one handling the window events of a GUI.
+///
+/// While paused, the consumer waits for the next item until the handle is resumed from another
+/// thread, once it drained the items already in the channel. The workers also stop waiting once
+/// `f` returns, so that a bridge left paused does not hang, dropping the iterator inside `f`
+/// while paused keeps them waiting until then.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::{par_bridge_pausable, PauseHandle};
+/// use rayon::prelude::*;
+///
+/// let pause = PauseHandle::new();
+/// let data = (0u32..100).collect::<Vec<_>>();
+///
+/// let count = par_bridge_pausable(5, data.into_par_iter(), &pause, |mut seq_iter| {
+///     let first = seq_iter.by_ref().take(10).count();
+///     pause.pause();
+///     // The window is in the background.
+///     std::thread::sleep(std::time::Duration::from_millis(10));
+///     pause.resume();
+///     first + seq_iter.count()
+/// });
+///
+/// assert_eq!(count, 100);
+/// ```
+pub fn par_bridge_pausable<I, F, R>(bound: usize, iter: I, pause: &PauseHandle, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let stopped = AtomicBool::new(false);
+    bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                pause.wait_while_paused(&stopped);
+                send.send(x).ok()
+            });
+        },
+        |seq_iter| {
+            // Releases the waiting workers even if `f` panics.
+            let _stop = Stop { pause, stopped: &stopped };
+            f(seq_iter)
+        },
+    )
+}
+
+/// Pauses and resumes the parallel pipelines of [`par_bridge_pausable`].
+///
+/// The clones of a handle share the same state, it starts resumed.
+#[derive(Clone, Default)]
+pub struct PauseHandle(Arc<(Mutex<bool>, Condvar)>);
+
+impl PauseHandle {
+    /// Creates a resumed handle.
+    pub fn new() -> PauseHandle {
+        PauseHandle::default()
+    }
+
+    /// Makes the Rayon workers wait before sending their next item.
+    pub fn pause(&self) {
+        *self.0 .0.lock().unwrap() = true;
+    }
+
+    /// Lets the waiting Rayon workers send their items again.
+    pub fn resume(&self) {
+        *self.0 .0.lock().unwrap() = false;
+        self.0 .1.notify_all();
+    }
+
+    /// Whether the handle is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.0 .0.lock().unwrap()
+    }
+
+    fn wait_while_paused(&self, stopped: &AtomicBool) {
+        let (paused, cond) = &*self.0;
+        let paused = paused.lock().unwrap();
+        let _paused =
+            cond.wait_while(paused, |&mut paused| paused && !stopped.load(Ordering::Relaxed));
+    }
+}
+
+/// Releases the workers waiting on a paused handle when dropped.
+struct Stop<'a> {
+    pause: &'a PauseHandle,
+    stopped: &'a AtomicBool,
+}
+
+impl Drop for Stop<'_> {
+    fn drop(&mut self) {
+        let (paused, cond) = &*self.pause.0;
+        // Holding the lock makes sure no worker is between its check and its wait.
+        let _paused = paused.lock().unwrap();
+        self.stopped.store(true, Ordering::Relaxed);
+        cond.notify_all();
+    }
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but stops the parallel pipeline
+/// once `limit` items have been sent.
+///
+/// Compared to `par_bridge(bound, iter, |seq_iter| f(seq_iter.take(limit)))`, the parallel
+/// pipeline stops right after sending its last item instead of waiting for the consumer to
+/// drop the iterator, which saves the work the Rayon workers would do in the meantime.
+/// The consumer sees at most `limit` items, which ones is arbitrary.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_take;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..10_000).collect::<Vec<_>>();
+/// let result: Vec<_> = par_bridge_take(5, 10, data.into_par_iter(), |seq_iter| seq_iter.collect());
+///
+/// assert_eq!(result.len(), 10);
+/// ```
+pub fn par_bridge_take<I, F, R>(bound: usize, limit: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let reserved = AtomicUsize::new(0);
+    bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                // Reserve a slot first so that concurrent workers never send more than `limit`.
+                if reserved.fetch_add(1, Ordering::Relaxed) >= limit {
+                    return None;
+                }
+                send.send(x).ok()
+            });
+        },
+        f,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::{self, ThreadId};
+    use std::time::{Duration, Instant};
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn stops_well_before_the_end() {
+        let cancel = AtomicBool::new(false);
+        let processed = AtomicUsize::new(0);
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let count = par_bridge_cancellable(5, parallel_pipeline, &cancel, |seq_iter| {
+            seq_iter
+                .enumerate()
+                .inspect(|&(i, _)| {
+                    if i == 9 {
+                        cancel.store(true, Ordering::Relaxed);
+                    }
+                })
+                .count()
+        });
+
+        assert!(count >= 10);
+        assert!(processed.load(Ordering::Relaxed) < 1_000);
+    }
+
+    #[test]
+    fn take_stops_the_producer() {
+        let computed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u64..1_000_000).into_par_iter().map(|num| {
+            computed.fetch_add(1, Ordering::Relaxed);
+            (0..1000).fold(num, |acc, i| std::hint::black_box(acc ^ i))
+        });
+
+        let count = par_bridge_take(5, 10, parallel_pipeline, Iterator::count);
+
+        assert_eq!(count, 10);
+        assert!(computed.load(Ordering::Relaxed) < 100_000);
+    }
+
+    #[test]
+    fn cancel_drains_the_items_in_flight() {
+        /// Counts the items dropped outside of the consumer thread.
+        struct Token<'a> {
+            consumer: ThreadId,
+            stray: &'a AtomicUsize,
+        }
+
+        impl Drop for Token<'_> {
+            fn drop(&mut self) {
+                if thread::current().id() != self.consumer {
+                    self.stray.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let consumer = thread::current().id();
+        let cancel = AtomicBool::new(false);
+        let (created, stray) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().map(|_| {
+            created.fetch_add(1, Ordering::Relaxed);
+            Token { consumer, stray: &stray }
+        });
+
+        let mut drained = 0;
+        let delivered = par_bridge_cancellable_drain(
+            4,
+            parallel_pipeline,
+            &cancel,
+            |_token| {
+                assert_eq!(thread::current().id(), consumer);
+                drained += 1;
+            },
+            |seq_iter| {
+                let delivered = seq_iter.take(10).count();
+                // Lets the parallel pipeline fill the channel.
+                thread::sleep(Duration::from_millis(100));
+                cancel.store(true, Ordering::Relaxed);
+                delivered
+            },
+        );
+
+        assert_eq!(delivered, 10);
+        // The channel was full, only the workers blocked on it can add more items.
+        assert!(drained >= 4 && drained <= 4 + rayon::current_num_threads(), "{drained}");
+        // The items never sent are dropped by the workers, every other one was drained.
+        let created = created.into_inner();
+        assert_eq!(delivered + drained + stray.into_inner(), created);
+        assert!(created < 10_000);
+    }
+
+    #[test]
+    fn pause_stalls_the_producer() {
+        let pause = PauseHandle::new();
+        let sent = AtomicUsize::new(0);
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().copied().inspect(|_| {
+            sent.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let (mut result, stalled) =
+            par_bridge_pausable(4, parallel_pipeline, &pause, |mut seq_iter| {
+                let mut result: Vec<_> = seq_iter.by_ref().take(100).collect();
+                pause.pause();
+                // Lets the workers reach the paused handle, then checks they stay there.
+                thread::sleep(Duration::from_millis(50));
+                let before = sent.load(Ordering::Relaxed);
+                let start = Instant::now();
+                thread::sleep(Duration::from_millis(100));
+                let stalled = sent.load(Ordering::Relaxed) == before;
+                pause.resume();
+
+                result.extend(seq_iter);
+                (result, stalled && start.elapsed() >= Duration::from_millis(100))
+            });
+
+        assert!(stalled, "items were produced while paused");
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn paused_bridge_does_not_hang() {
+        let pause = PauseHandle::new();
+        let count = par_bridge_pausable(4, (0u32..10_000).into_par_iter(), &pause, |seq_iter| {
+            let count = seq_iter.take(3).count();
+            pause.pause();
+            count
+        });
+
+        assert_eq!(count, 3);
+        assert!(pause.is_paused());
+    }
+}
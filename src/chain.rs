@@ -0,0 +1,190 @@
+use rayon::iter::IntoParallelIterator;
+
+use crate::par_bridge;
+
+/// A step of the sequential side of a bridge, see [`par_bridge_stages`].
+///
+/// A stage is called with each item in turn, on the consumer thread, and can keep any state
+/// between the calls. Returning `None` filters the item out, the following stages never see it.
+///
+/// Stages are chained by putting them in a pair or a triple, which can be nested to chain more
+/// of them, or in a `Vec` of boxed stages when they all keep the item type. A `Box` of a stage
+/// is a stage too, and so is a mutable reference, which keeps the state of the stage with the
+/// caller.
+pub trait SeqStage<T> {
+    /// The type of the items passed to the next stage.
+    type Output;
+
+    /// Processes an item, returns what the next stage gets, if anything.
+    fn process(&mut self, item: T) -> Option<Self::Output>;
+}
+
+impl<T, S: SeqStage<T> + ?Sized> SeqStage<T> for Box<S> {
+    type Output = S::Output;
+
+    fn process(&mut self, item: T) -> Option<Self::Output> {
+        (**self).process(item)
+    }
+}
+
+impl<T, S: SeqStage<T> + ?Sized> SeqStage<T> for &mut S {
+    type Output = S::Output;
+
+    fn process(&mut self, item: T) -> Option<Self::Output> {
+        (**self).process(item)
+    }
+}
+
+impl<T, A, B> SeqStage<T> for (A, B)
+where
+    A: SeqStage<T>,
+    B: SeqStage<A::Output>,
+{
+    type Output = B::Output;
+
+    fn process(&mut self, item: T) -> Option<Self::Output> {
+        self.1.process(self.0.process(item)?)
+    }
+}
+
+impl<T, A, B, C> SeqStage<T> for (A, B, C)
+where
+    A: SeqStage<T>,
+    B: SeqStage<A::Output>,
+    C: SeqStage<B::Output>,
+{
+    type Output = C::Output;
+
+    fn process(&mut self, item: T) -> Option<Self::Output> {
+        self.2.process(self.1.process(self.0.process(item)?)?)
+    }
+}
+
+impl<T> SeqStage<T> for Vec<Box<dyn SeqStage<T, Output = T> + '_>> {
+    type Output = T;
+
+    fn process(&mut self, item: T) -> Option<Self::Output> {
+        self.iter_mut().try_fold(item, |item, stage| stage.process(item))
+    }
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but the items are consumed by a chain of
+/// sequential `stages` instead of a closure, and the stages are returned once every item went
+/// through them.
+///
+/// Each item is passed through the stages in order, on the current thread, as soon as it is
+/// received. The outputs of the last stage are dropped: end the chain with a stage that
+/// accumulates them, like a fold, and read its state from the stages returned.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::{par_bridge_stages, SeqStage};
+/// use rayon::prelude::*;
+///
+/// struct Parse;
+///
+/// impl SeqStage<String> for Parse {
+///     type Output = u32;
+///
+///     fn process(&mut self, line: String) -> Option<u32> {
+///         line.parse().ok()
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct Sum(u32);
+///
+/// impl SeqStage<u32> for Sum {
+///     type Output = ();
+///
+///     fn process(&mut self, num: u32) -> Option<()> {
+///         self.0 += num;
+///         None
+///     }
+/// }
+///
+/// let lines = (0u32..100).map(|num| num.to_string()).collect::<Vec<_>>();
+/// let (_, sum) = par_bridge_stages(5, lines.into_par_iter(), (Parse, Sum::default()));
+///
+/// assert_eq!(sum.0, 4950);
+/// ```
+pub fn par_bridge_stages<I, S>(bound: usize, iter: I, mut stages: S) -> S
+where
+    I: IntoParallelIterator + Send,
+    S: SeqStage<I::Item>,
+{
+    par_bridge(bound, iter, |seq_iter| {
+        for item in seq_iter {
+            stages.process(item);
+        }
+    });
+    stages
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    struct Square;
+
+    impl SeqStage<u64> for Square {
+        type Output = u64;
+
+        fn process(&mut self, num: u64) -> Option<u64> {
+            Some(num * num)
+        }
+    }
+
+    struct Odd;
+
+    impl SeqStage<u64> for Odd {
+        type Output = u64;
+
+        fn process(&mut self, num: u64) -> Option<u64> {
+            (num % 2 == 1).then_some(num)
+        }
+    }
+
+    #[derive(Default)]
+    struct Total {
+        sum: u64,
+        count: usize,
+    }
+
+    impl SeqStage<u64> for Total {
+        type Output = ();
+
+        fn process(&mut self, num: u64) -> Option<()> {
+            self.sum += num;
+            self.count += 1;
+            None
+        }
+    }
+
+    #[test]
+    fn three_stages_match_the_inlined_closure() {
+        let data = (0u64..1000).collect::<Vec<_>>();
+        let inlined = par_bridge(5, data.par_iter().copied(), |seq_iter| {
+            seq_iter
+                .map(|num| num * num)
+                .filter(|num| num % 2 == 1)
+                .fold((0, 0), |acc, num| (acc.0 + num, acc.1 + 1))
+        });
+
+        let (_, _, total) =
+            par_bridge_stages(5, data.par_iter().copied(), (Square, Odd, Total::default()));
+        assert_eq!((total.sum, total.count), inlined);
+
+        // Stages of the same type boxed in a `Vec`, the last one is borrowed.
+        let mut total = Total::default();
+        let stages: Vec<Box<dyn SeqStage<u64, Output = u64>>> =
+            vec![Box::new(Square), Box::new(Odd), Box::new(Square)];
+        par_bridge_stages(5, data.par_iter().copied(), (stages, &mut total));
+        let expected = data.iter().map(|num| num * num).filter(|num| num % 2 == 1);
+        assert_eq!(total.sum, expected.map(|num| num * num).sum::<u64>());
+        assert_eq!(total.count, inlined.1);
+    }
+}
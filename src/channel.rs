@@ -0,0 +1,450 @@
+//! The channel carrying the items from the parallel pipeline to the consumer.
+//!
+//! Every bridge goes through these types, so that the channel implementation can be swapped
+//! in a single place without touching the public API. The items go through a
+//! `std::sync::mpsc` channel, or a `crossbeam-channel` one with the `crossbeam` feature, which
+//! holds up better when many Rayon workers send at the same time.
+
+use std::cell::{Cell, RefCell};
+use std::hint;
+use std::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
+pub(crate) use std::sync::mpsc::TryRecvError;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+#[cfg(not(feature = "crossbeam"))]
+use std::thread;
+use std::time::Duration;
+
+use rayon::Yield;
+
+use self::backend::TrySendError;
+use crate::trace::{self, Span};
+
+/// The channel wrapped by the types of this module.
+#[cfg(not(feature = "crossbeam"))]
+mod backend {
+    pub(super) use std::sync::mpsc::{
+        channel as unbounded, sync_channel as bounded, Receiver, RecvTimeoutError,
+        Sender as UnboundedSender, SyncSender as BoundedSender, TryRecvError, TrySendError,
+    };
+}
+
+/// The channel wrapped by the types of this module.
+#[cfg(feature = "crossbeam")]
+mod backend {
+    pub(super) use crossbeam_channel::{
+        bounded, unbounded, Receiver, RecvTimeoutError, Sender as BoundedSender,
+        Sender as UnboundedSender, TryRecvError, TrySendError,
+    };
+}
+
+/// How long a Rayon worker waiting for room, while the consumer runs a nested bridge, first
+/// waits before looking for other tasks again.
+const MIN_BACKOFF: Duration = Duration::from_micros(50);
+/// The longest a Rayon worker waiting for room, while the consumer runs a nested bridge, waits
+/// before looking for other tasks again.
+const MAX_BACKOFF: Duration = Duration::from_millis(1);
+/// How many times a Rayon worker waiting for room spins and tries again before sleeping.
+const SPIN_STEPS: u32 = 4;
+/// How many tasks a Rayon worker waiting for room can run within each other.
+const MAX_NESTED: usize = 8;
+/// How long a consumer of a [`SharedReceiver`] first sleeps when the channel is empty.
+#[cfg(not(feature = "crossbeam"))]
+const MIN_POLL_BACKOFF: Duration = Duration::from_micros(50);
+/// The longest a consumer of a [`SharedReceiver`] sleeps before looking for an item again.
+#[cfg(not(feature = "crossbeam"))]
+const MAX_POLL_BACKOFF: Duration = Duration::from_millis(1);
+
+/// The sending half of a bounded or unbounded channel.
+pub(crate) struct Sender<T> {
+    flavor: Flavor<T>,
+    /// Tells the receiving half whether any sender is left, see [`Receiver::is_disconnected`].
+    _alive: Arc<()>,
+    /// The span of the parallel pipeline sending the items, see [`Sender::in_span`].
+    span: Span,
+}
+
+enum Flavor<T> {
+    /// The room is shared with the receiving half, unless the channel has no room at all.
+    Bounded(backend::BoundedSender<T>, Option<Arc<Room>>),
+    Unbounded(backend::UnboundedSender<T>),
+}
+
+impl<T> Sender<T> {
+    /// Sends an item, waiting while a bounded channel is full.
+    ///
+    /// While the consumer runs a nested bridge, see [`Receiver::consuming`], a Rayon worker
+    /// does not only wait: it runs the other tasks of its pool in the meantime, like the
+    /// pipeline of the nested bridge, which would never run if every worker was waiting for
+    /// the consumer. A channel of zero items is the exception, the worker blocks until the
+    /// consumer takes its item.
+    ///
+    /// Fails when the receiving half has been dropped.
+    pub(crate) fn send(&self, item: T) -> Result<(), SendError<T>> {
+        match &self.flavor {
+            Flavor::Bounded(send, Some(room)) if rayon::current_thread_index().is_some() => {
+                room.send(send, item)
+            }
+            Flavor::Bounded(send, _) => send.send(item).map_err(|e| SendError(e.0)),
+            Flavor::Unbounded(send) => send.send(item).map_err(|e| SendError(e.0)),
+        }?;
+        trace::sent(&self.span);
+        Ok(())
+    }
+
+    /// Sets the span the events of this sender, and of its clones, belong to.
+    pub(crate) fn in_span(self, span: Span) -> Sender<T> {
+        Sender { span, ..self }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        let flavor = match &self.flavor {
+            Flavor::Bounded(send, room) => Flavor::Bounded(send.clone(), room.clone()),
+            Flavor::Unbounded(send) => Flavor::Unbounded(send.clone()),
+        };
+        Sender { flavor, _alive: self._alive.clone(), span: self.span.clone() }
+    }
+}
+
+/// Wakes up the Rayon workers waiting for room in a bounded channel.
+#[derive(Default)]
+struct Room {
+    /// The number of workers waiting on `freed`.
+    waiting: AtomicUsize,
+    /// The number of bridges the consumer runs within its own, see [`Receiver::consuming`].
+    nested: AtomicUsize,
+    /// Set when the receiving half is dropped, just before the channel is disconnected.
+    closed: AtomicBool,
+    lock: Mutex<()>,
+    /// Notified when an item is received, the receiving half is dropped, or the consumer
+    /// starts a nested bridge.
+    freed: Condvar,
+}
+
+impl Room {
+    /// Sends an item, running other Rayon tasks while the channel is full.
+    fn send<T>(&self, send: &backend::BoundedSender<T>, mut item: T) -> Result<(), SendError<T>> {
+        let mut backoff = MIN_BACKOFF;
+        let mut step = 0;
+        loop {
+            match send.try_send(item) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Disconnected(x)) => return Err(SendError(x)),
+                Err(TrySendError::Full(x)) => item = x,
+            }
+            // Room is often made shortly, it is cheaper to spin a bit than to sleep and be
+            // woken up, like the channel does.
+            if step < SPIN_STEPS {
+                (0..1 << step).for_each(|_| hint::spin_loop());
+                step += 1;
+                continue;
+            }
+            if self.nested.load(Ordering::SeqCst) > 0 && yield_nested() {
+                backoff = MIN_BACKOFF;
+                continue;
+            }
+
+            // No other task to run, waits for room, or for some time to look for tasks again
+            // while the consumer runs a nested bridge.
+            let guard = self.lock.lock().unwrap();
+            self.waiting.fetch_add(1, Ordering::SeqCst);
+            // Pairs with the fence of `freed_one`: either the consumer sees this worker waiting,
+            // or this worker sees the room the consumer made.
+            atomic::fence(Ordering::SeqCst);
+            let x = match send.try_send(item) {
+                Err(TrySendError::Full(x)) if !self.closed.load(Ordering::SeqCst) => x,
+                sent => {
+                    self.waiting.fetch_sub(1, Ordering::SeqCst);
+                    return sent.map_err(|e| match e {
+                        TrySendError::Disconnected(x) | TrySendError::Full(x) => SendError(x),
+                    });
+                }
+            };
+            item = x;
+            // The worker is counted out of `waiting` by the one notifying it. A worker that
+            // times out is still counted, which only costs a notification nobody receives.
+            if self.nested.load(Ordering::SeqCst) > 0 {
+                let _ = self.freed.wait_timeout(guard, backoff).unwrap();
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            } else {
+                let _guard = self.freed.wait(guard).unwrap();
+            }
+        }
+    }
+
+    fn freed_one(&self) {
+        atomic::fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::SeqCst) > 0 {
+            let guard = self.lock.lock().unwrap();
+            if self.waiting.load(Ordering::SeqCst) > 0 {
+                self.waiting.fetch_sub(1, Ordering::SeqCst);
+                // Notifying without the lock keeps the worker from waking up only to wait for it.
+                drop(guard);
+                self.freed.notify_one();
+            }
+        }
+    }
+
+    fn close(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.closed.store(true, Ordering::SeqCst);
+        self.waiting.store(0, Ordering::SeqCst);
+        self.freed.notify_all();
+    }
+
+    fn nest(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.nested.fetch_add(1, Ordering::SeqCst);
+        self.waiting.store(0, Ordering::SeqCst);
+        self.freed.notify_all();
+    }
+
+    fn unnest(&self) {
+        self.nested.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+thread_local! {
+    /// The number of tasks the current worker runs within each other while waiting for room.
+    static NESTED: Cell<usize> = const { Cell::new(0) };
+    /// The rooms of the channels the current thread is consuming, innermost last.
+    static CONSUMING: RefCell<Vec<Option<Arc<Room>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs another Rayon task and returns whether there was one.
+///
+/// The task is often another part of the same pipeline, that would wait and run another one
+/// in turn until the stack overflows, the nested waits stop running tasks past a few levels.
+fn yield_nested() -> bool {
+    let nested = NESTED.get();
+    if nested >= MAX_NESTED {
+        return false;
+    }
+    NESTED.set(nested + 1);
+    let executed = rayon::yield_now() == Some(Yield::Executed);
+    NESTED.set(nested);
+    executed
+}
+
+/// The receiving half of a bounded or unbounded channel.
+pub(crate) struct Receiver<T> {
+    recv: backend::Receiver<T>,
+    senders: Weak<()>,
+    room: Option<Arc<Room>>,
+    /// The number of items the channel can hold, `None` if it is unbounded.
+    bound: Option<usize>,
+}
+
+impl<T> Receiver<T> {
+    /// Waits for an item, fails once the channel is empty and disconnected.
+    pub(crate) fn recv(&self) -> Result<T, RecvError> {
+        let item = self.recv.recv().map_err(|_| RecvError)?;
+        self.freed_one();
+        Ok(item)
+    }
+
+    /// Receives an item if one is already in the channel.
+    pub(crate) fn try_recv(&self) -> Result<T, TryRecvError> {
+        let item = self.recv.try_recv().map_err(|e| match e {
+            backend::TryRecvError::Empty => TryRecvError::Empty,
+            backend::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        })?;
+        self.freed_one();
+        Ok(item)
+    }
+
+    /// Waits at most `timeout` for an item, fails once the channel is empty and disconnected.
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let item = self.recv.recv_timeout(timeout).map_err(|e| match e {
+            backend::RecvTimeoutError::Timeout => RecvTimeoutError::Timeout,
+            backend::RecvTimeoutError::Disconnected => RecvTimeoutError::Disconnected,
+        })?;
+        self.freed_one();
+        Ok(item)
+    }
+
+    /// Whether every sender was dropped, even if items are still in the channel.
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.senders.strong_count() == 0
+    }
+
+    /// The number of items the channel can hold, `None` if it is unbounded.
+    pub(crate) fn bound(&self) -> Option<usize> {
+        self.bound
+    }
+
+    /// Marks the current thread as the consumer of this channel until the guard is dropped.
+    ///
+    /// The Rayon workers waiting for room in the channels the current thread was already
+    /// consuming start running other tasks, as the pipeline of this channel may need them.
+    pub(crate) fn consuming(&self) -> Consuming {
+        CONSUMING.with_borrow_mut(|rooms| {
+            rooms.iter().flatten().for_each(|room| room.nest());
+            rooms.push(self.room.clone());
+        });
+        Consuming(())
+    }
+
+    fn freed_one(&self) {
+        if let Some(room) = &self.room {
+            room.freed_one();
+        }
+    }
+}
+
+/// Returned by [`Receiver::consuming`].
+pub(crate) struct Consuming(());
+
+impl Drop for Consuming {
+    fn drop(&mut self) {
+        CONSUMING.with_borrow_mut(|rooms| {
+            rooms.pop();
+            rooms.iter().flatten().for_each(|room| room.unnest());
+        });
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if let Some(room) = &self.room {
+            room.close();
+        }
+        // A crossbeam channel keeps its items until the senders are dropped too, on the
+        // producer thread, while the items buffered are expected to be dropped here. Only
+        // the items already there are, the workers could otherwise keep refilling it.
+        #[cfg(feature = "crossbeam")]
+        self.recv.try_iter().take(self.recv.len()).for_each(drop);
+    }
+}
+
+/// The receiving half of a channel that several consumers receive from at the same time.
+///
+/// A crossbeam receiver is shared as is, the consumers wait for the items together. A mpsc
+/// receiver can only be used by one thread at a time: the consumers take turns looking for an
+/// item and sleep, without the receiver, while the channel is empty.
+pub(crate) struct SharedReceiver<T> {
+    #[cfg(feature = "crossbeam")]
+    recv: Arc<Receiver<T>>,
+    #[cfg(not(feature = "crossbeam"))]
+    recv: Arc<Mutex<Receiver<T>>>,
+}
+
+impl<T> SharedReceiver<T> {
+    pub(crate) fn new(recv: Receiver<T>) -> SharedReceiver<T> {
+        #[cfg(feature = "crossbeam")]
+        let recv = Arc::new(recv);
+        #[cfg(not(feature = "crossbeam"))]
+        let recv = Arc::new(Mutex::new(recv));
+        SharedReceiver { recv }
+    }
+
+    /// Receives an item, waiting for one if `block` is set and the channel is empty.
+    #[cfg(feature = "crossbeam")]
+    pub(crate) fn recv(&self, block: bool) -> Result<T, TryRecvError> {
+        if block {
+            self.recv.recv().map_err(|_| TryRecvError::Disconnected)
+        } else {
+            self.recv.try_recv()
+        }
+    }
+
+    /// Receives an item, waiting for one if `block` is set and the channel is empty.
+    #[cfg(not(feature = "crossbeam"))]
+    pub(crate) fn recv(&self, block: bool) -> Result<T, TryRecvError> {
+        let mut backoff = MIN_POLL_BACKOFF;
+        loop {
+            match self.recv.lock().unwrap().try_recv() {
+                Err(TryRecvError::Empty) if block => (),
+                item => return item,
+            }
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_POLL_BACKOFF);
+        }
+    }
+
+    /// Whether every sender was dropped, even if items are still in the channel.
+    pub(crate) fn is_disconnected(&self) -> bool {
+        #[cfg(feature = "crossbeam")]
+        return self.recv.is_disconnected();
+        #[cfg(not(feature = "crossbeam"))]
+        return self.recv.lock().unwrap().is_disconnected();
+    }
+}
+
+impl<T> Clone for SharedReceiver<T> {
+    fn clone(&self) -> SharedReceiver<T> {
+        SharedReceiver { recv: self.recv.clone() }
+    }
+}
+
+/// Creates a channel holding up to `bound` items.
+pub(crate) fn bounded<T>(bound: usize) -> (Sender<T>, Receiver<T>) {
+    let (send, recv) = backend::bounded(bound);
+    let room = (bound != 0).then(Arc::default);
+    with_flavor(Flavor::Bounded(send, room.clone()), recv, room, Some(bound))
+}
+
+/// Creates a channel that never blocks the sender.
+pub(crate) fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    let (send, recv) = backend::unbounded();
+    with_flavor(Flavor::Unbounded(send), recv, None, None)
+}
+
+fn with_flavor<T>(
+    flavor: Flavor<T>,
+    recv: backend::Receiver<T>,
+    room: Option<Arc<Room>>,
+    bound: Option<usize>,
+) -> (Sender<T>, Receiver<T>) {
+    let alive = Arc::new(());
+    let senders = Arc::downgrade(&alive);
+    let send = Sender { flavor, _alive: alive, span: Span::none() };
+    (send, Receiver { recv, senders, room, bound })
+}
+
+/// Iterates over the items of a channel until it is disconnected.
+pub(crate) struct IntoIter<T> {
+    recv: Receiver<T>,
+    _lent: Lent,
+}
+
+impl<T> IntoIter<T> {
+    pub(crate) fn new(recv: Receiver<T>, lent: &Lent) -> IntoIter<T> {
+        IntoIter { recv, _lent: lent.clone() }
+    }
+
+    /// Receives an item, waiting for one if `block` is set and the channel is empty.
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        if block {
+            self.recv.recv().map_err(|_| TryRecvError::Disconnected)
+        } else {
+            self.recv.try_recv()
+        }
+    }
+
+    /// Whether every sender was dropped, even if items are still in the channel.
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.recv.recv().ok()
+    }
+}
+
+/// Tells whether the receiving half of a channel lent to a consumer outlived it.
+#[derive(Clone, Default)]
+pub(crate) struct Lent(Arc<()>);
+
+impl Lent {
+    /// Whether no clone of this value, held by the receiving half, is left.
+    pub(crate) fn is_returned(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+}
@@ -0,0 +1,156 @@
+use rayon::iter::IntoParallelIterator;
+
+use crate::{par_bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`] but the consumer receives the items in chunks
+/// of `chunk_size` items, the last chunk being shorter if needed.
+///
+/// Unlike [`par_bridge_batched`](crate::par_bridge_batched), the items are still sent one by
+/// one through the channel, they are only grouped on the consumer side.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_chunks;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let sizes: Vec<_> = par_bridge_chunks(5, 30, data.into_par_iter(), |chunks| {
+///     chunks.map(|chunk| chunk.len()).collect()
+/// });
+///
+/// assert_eq!(sizes, [30, 30, 30, 10]);
+/// ```
+pub fn par_bridge_chunks<I, F, R>(bound: usize, chunk_size: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonChunks<I::Item>) -> R,
+{
+    assert!(chunk_size != 0, "chunk size must be non-zero");
+    par_bridge(bound, iter, |inner| f(RayonChunks { inner, chunk_size }))
+}
+
+/// An `Iterator` over chunks of the elements returned by a parallel rayon pipeline.
+pub struct RayonChunks<T> {
+    inner: RayonIntoIter<T>,
+    chunk_size: usize,
+}
+
+impl<T> Iterator for RayonChunks<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk: Vec<_> = self.inner.by_ref().take(self.chunk_size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+/// Same as [`par_bridge`] but the consumer receives the items two by two, in completion order.
+///
+/// This suits merging the items pairwise. The pairs are only formed on the consumer side,
+/// when the number of items is odd the last one is not yielded in a pair but kept aside, for
+/// [`RayonPairs::tail`] to return.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_pairs;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..101).collect::<Vec<_>>();
+/// let (pairs, tail) = par_bridge_pairs(5, data.into_par_iter(), |mut pairs| {
+///     let count = pairs.by_ref().count();
+///     (count, pairs.tail())
+/// });
+///
+/// assert_eq!(pairs, 50);
+/// assert!(tail.is_some());
+/// ```
+pub fn par_bridge_pairs<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonPairs<I::Item>) -> R,
+{
+    par_bridge(bound, iter, |inner| f(RayonPairs { inner, tail: None }))
+}
+
+/// An `Iterator` over pairs of the elements returned by a parallel rayon pipeline,
+/// see [`par_bridge_pairs`].
+pub struct RayonPairs<T> {
+    inner: RayonIntoIter<T>,
+    tail: Option<T>,
+}
+
+impl<T> RayonPairs<T> {
+    /// Returns the last item of an odd number of items, which could not be paired.
+    ///
+    /// It is only known once the iterator returned `None`, this returns `None` before that.
+    pub fn tail(&mut self) -> Option<T> {
+        self.tail.take()
+    }
+}
+
+impl<T> Iterator for RayonPairs<T> {
+    type Item = (T, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.inner.next()?;
+        match self.inner.next() {
+            Some(second) => Some((first, second)),
+            None => {
+                self.tail = Some(first);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn last_chunk_is_emitted() {
+        // 98 is a multiple of 7, 100 is not.
+        for (total, last_chunk_size) in [(98, 7), (100, 2)] {
+            let data = (0u32..total).collect::<Vec<_>>();
+            let chunks: Vec<_> = par_bridge_chunks(5, 7, data.into_par_iter(), Iterator::collect);
+
+            let (last, others) = chunks.split_last().unwrap();
+            assert!(others.iter().all(|chunk| chunk.len() == 7));
+            assert_eq!(last.len(), last_chunk_size);
+
+            let mut items: Vec<_> = chunks.into_iter().flatten().collect();
+            items.sort_unstable();
+            assert_eq!(items, (0..total).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn odd_item_is_the_tail() {
+        for total in [100, 101] {
+            let data = (0u32..total).collect::<Vec<_>>();
+            let (pairs, tail) = par_bridge_pairs(5, data.into_par_iter(), |mut pairs| {
+                let collected: Vec<_> = pairs.by_ref().collect();
+                (collected, pairs.tail())
+            });
+
+            assert_eq!(pairs.len(), total as usize / 2);
+            assert_eq!(tail.is_some(), total % 2 == 1);
+            let mut items: Vec<_> =
+                pairs.into_iter().flat_map(|(a, b)| [a, b]).chain(tail).collect();
+            items.sort_unstable();
+            assert_eq!(items, (0..total).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,1038 @@
+//! Shorthands for the most common consumers.
+
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::iter::{Enumerate, Inspect, Zip};
+use std::vec;
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::{par_bridge, par_bridge_exact, try_par_bridge, RayonIntoIter};
+
+/// Calls `g` on each item of the parallel pipeline, sequentially on the current thread.
+///
+/// This is a shorthand for `par_bridge(bound, iter, |seq_iter| seq_iter.for_each(g))`,
+/// as `g` runs on a single thread it can freely mutate the state it captures.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_for_each;
+/// use rayon::prelude::*;
+///
+/// let data = (0u64..100).collect::<Vec<_>>();
+/// let mut sum = 0;
+/// par_bridge_for_each(5, data.into_par_iter().map(|num| num * 2), |num| sum += num);
+///
+/// assert_eq!(sum, 9900);
+/// ```
+pub fn par_bridge_for_each<I, G>(bound: usize, iter: I, g: G)
+where
+    I: IntoParallelIterator + Send,
+    G: FnMut(I::Item),
+{
+    par_bridge(bound, iter, |seq_iter| seq_iter.for_each(g))
+}
+
+/// Folds the items of the parallel pipeline into an accumulator, sequentially on the
+/// current thread.
+///
+/// This is a shorthand for `par_bridge(bound, iter, |seq_iter| seq_iter.fold(init, fold))`,
+/// the accumulator never leaves the current thread and does not need to be `Send`.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use rayon_par_bridge::par_bridge_fold;
+/// use rayon::prelude::*;
+///
+/// let data = (0u64..100).collect::<Vec<_>>();
+/// let sum = par_bridge_fold(5, data.into_par_iter(), Rc::new(0), |sum, num| Rc::new(*sum + num));
+///
+/// assert_eq!(*sum, 4950);
+/// ```
+pub fn par_bridge_fold<I, B, Fold>(bound: usize, iter: I, init: B, fold: Fold) -> B
+where
+    I: IntoParallelIterator + Send,
+    Fold: FnMut(B, I::Item) -> B,
+{
+    par_bridge(bound, iter, |seq_iter| seq_iter.fold(init, fold))
+}
+
+/// Same as [`par_bridge`] but drains the items `f` did not consume once it returns, so that
+/// every item is dropped on the current thread.
+///
+/// This is meant for items whose `Drop` must run on a specific thread, like types doing
+/// thread-local cleanup. Stopping early no longer stops the parallel pipeline: the remaining
+/// items are still computed, then received and dropped one by one before this function
+/// returns. Items discarded by the pipeline itself, like the ones rejected by a `filter`,
+/// are still dropped on the Rayon workers.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_drain;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).map(|num| num.to_string()).collect::<Vec<_>>();
+/// let first = par_bridge_drain(5, data.into_par_iter(), |seq_iter| seq_iter.next());
+///
+/// assert!(first.is_some());
+/// ```
+pub fn par_bridge_drain<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(&mut RayonIntoIter<I::Item>) -> R,
+{
+    par_bridge(bound, iter, |mut seq_iter| {
+        let result = f(&mut seq_iter);
+        seq_iter.for_each(drop);
+        result
+    })
+}
+
+/// Reduces the items of the parallel pipeline on the Rayon workers and only sends the partial
+/// results through the channel, for `f` to merge them sequentially.
+///
+/// Each Rayon work unit reduces its items with `op`, starting from `identity()`, and sends its
+/// partial result once done, so the consumer sees a handful of partials instead of every item.
+/// The partials arrive in completion order, `op` must therefore be associative and the merge
+/// done by `f` must not depend on the order of the partials.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_reduce;
+/// use rayon::prelude::*;
+///
+/// let data = (0u64..1000).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * num);
+///
+/// let sum = par_bridge_reduce(5, parallel_pipeline, || 0, |a, b| a + b, |partials| {
+///     partials.sum::<u64>()
+/// });
+/// assert_eq!(sum, 332_833_500);
+/// ```
+pub fn par_bridge_reduce<I, ID, Op, F, R>(bound: usize, iter: I, identity: ID, op: Op, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    ID: Fn() -> I::Item + Sync + Send,
+    Op: Fn(I::Item, I::Item) -> I::Item + Sync + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    par_bridge_per_chunk(bound, identity, op, iter, f)
+}
+
+/// Folds the items of each Rayon work unit into an accumulator on the workers and sends the
+/// accumulators through the channel, for `f` to merge them sequentially.
+///
+/// Each work unit starts from `init()` and folds its items with `fold`, the accumulator is sent
+/// once the work unit is done. There is about one accumulator per split made by Rayon, usually
+/// a few times the number of threads, which saves most of the channel traffic of reduction-like
+/// workloads. The accumulators arrive in completion order.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_per_chunk;
+/// use rayon::prelude::*;
+///
+/// let words = vec!["apple", "banana", "cherry", "date"];
+/// let letters = par_bridge_per_chunk(
+///     5,
+///     String::new,
+///     |mut acc, word: &str| {
+///         acc.push_str(word);
+///         acc
+///     },
+///     words.into_par_iter(),
+///     |chunks| chunks.map(|chunk| chunk.len()).sum::<usize>(),
+/// );
+///
+/// assert_eq!(letters, 21);
+/// ```
+pub fn par_bridge_per_chunk<I, Acc, Init, Fold, F, R>(
+    bound: usize,
+    init: Init,
+    fold: Fold,
+    iter: I,
+    f: F,
+) -> R
+where
+    I: IntoParallelIterator + Send,
+    Acc: Send,
+    Init: Fn() -> Acc + Sync + Send,
+    Fold: Fn(Acc, I::Item) -> Acc + Sync + Send,
+    F: FnOnce(RayonIntoIter<Acc>) -> R,
+{
+    par_bridge(bound, iter.into_par_iter().fold(init, fold), f)
+}
+
+/// Same as [`par_bridge`] but pairs each item with the order in which it is delivered to `f`.
+///
+/// The index is assigned on the current thread as the items are pulled, it is gap-free and
+/// starts at zero, like output line numbers. It has nothing to do with the position of the
+/// item in the parallel input, use [`par_bridge_ordered`](crate::par_bridge_ordered) for that.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_enumerate;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let lines: Vec<_> = par_bridge_enumerate(5, data.into_par_iter(), |seq_iter| {
+///     seq_iter.map(|(line, num)| format!("{line}: {num}")).collect()
+/// });
+///
+/// assert!(lines[0].starts_with("0: "));
+/// ```
+pub fn par_bridge_enumerate<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(Enumerate<RayonIntoIter<I::Item>>) -> R,
+{
+    par_bridge(bound, iter, |seq_iter| f(seq_iter.enumerate()))
+}
+
+/// Same as [`par_bridge`] but pairs each item with the next one of the sequential iterator
+/// `seq`, and stops when either side is exhausted.
+///
+/// The pairing is by arrival position: the first item delivered to `f` is paired with the first
+/// one of `seq`, and so on. As the items arrive in completion order, a pair is not related to
+/// the position of the item in the parallel input nor to any key, only to the order in which the
+/// items happened to be delivered. When `seq` ends first, the pairs end too and the rest of the
+/// parallel pipeline is stopped once `f` returns, like after an early stop.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_zip;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let headers = ["first", "second", "third"];
+/// let pairs: Vec<_> = par_bridge_zip(5, data.into_par_iter(), headers, Iterator::collect);
+///
+/// assert_eq!(pairs.len(), 3);
+/// assert_eq!(pairs[0].1, "first");
+/// ```
+pub fn par_bridge_zip<I, S, F, R>(bound: usize, iter: I, seq: S, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    S: IntoIterator,
+    F: FnOnce(Zip<RayonIntoIter<I::Item>, S::IntoIter>) -> R,
+{
+    par_bridge(bound, iter, |seq_iter| f(seq_iter.zip(seq)))
+}
+
+/// Same as [`par_bridge`] but calls `tap` on each item, sequentially on the current thread,
+/// right before it is yielded to `f`.
+///
+/// `tap` sees the items in the order `f` receives them, and as it runs on the current thread it
+/// can freely mutate the state it captures, to sample or log the items for example.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_inspect;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let mut seen = 0;
+/// let count = par_bridge_inspect(5, data.into_par_iter(), |_| seen += 1, Iterator::count);
+///
+/// assert_eq!(seen, count);
+/// ```
+pub fn par_bridge_inspect<I, F, R, T>(bound: usize, iter: I, tap: T, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(Inspect<RayonIntoIter<I::Item>, T>) -> R,
+    T: FnMut(&I::Item),
+{
+    par_bridge(bound, iter, |seq_iter| f(seq_iter.inspect(tap)))
+}
+
+/// Counts the items of the parallel pipeline.
+///
+/// This returns the same count as `par_bridge(bound, iter, Iterator::count)`, but the items are
+/// counted and dropped on the Rayon workers and nothing goes through a channel, which saves
+/// moving large items across threads only to drop them. This is Rayon's own
+/// [`ParallelIterator::count`], there is no channel to bound.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_count;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let count = par_bridge_count(data.into_par_iter().filter(|num| num % 2 == 0));
+///
+/// assert_eq!(count, 50);
+/// ```
+pub fn par_bridge_count<I>(iter: I) -> usize
+where
+    I: IntoParallelIterator + Send,
+{
+    iter.into_par_iter().count()
+}
+
+/// Same as [`par_bridge`] but the consumer receives the items sorted by `key`.
+///
+/// The items are still produced in parallel, but every one of them is buffered on the current
+/// thread until the parallel pipeline is done, before being sorted and handed to `f`. This is
+/// only suitable for pipelines whose whole output fits in memory. The sort is stable, items
+/// with equal keys keep the order in which they were received.
+///
+/// As the items are all there, `f` receives a [`DoubleEndedIterator`] and can consume them from
+/// both ends, or in reverse order with `rev`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_sorted_by_key;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let result: Vec<_> =
+///     par_bridge_sorted_by_key(5, parallel_pipeline, |&num| num, |seq_iter| seq_iter.collect());
+/// assert_eq!(result, (0u32..100).map(|num| num * 2).collect::<Vec<_>>());
+/// ```
+pub fn par_bridge_sorted_by_key<I, K, Key, F, R>(bound: usize, iter: I, key: Key, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    K: Ord,
+    Key: FnMut(&I::Item) -> K,
+    F: FnOnce(vec::IntoIter<I::Item>) -> R,
+{
+    let mut items: Vec<_> = par_bridge(bound, iter, Iterator::collect);
+    items.sort_by_key(key);
+    f(items.into_iter())
+}
+
+/// Same as [`par_bridge`] but `scan` turns each item into a derived value, sequentially on the
+/// current thread, carrying `state` from one item to the next.
+///
+/// The parallel pipeline cannot keep a state across items, the consumer can: as `scan` runs on
+/// a single thread, it can freely mutate the state, to compute running totals or the
+/// differences between consecutive items for example. The items are scanned in the order they
+/// are received.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_scan;
+/// use rayon::prelude::*;
+///
+/// let data = vec![1u32; 100];
+/// let totals: Vec<_> = par_bridge_scan(
+///     5,
+///     data.into_par_iter(),
+///     0,
+///     |total, num| {
+///         *total += num;
+///         *total
+///     },
+///     |seq_iter| seq_iter.collect(),
+/// );
+///
+/// assert_eq!(totals, (1..=100).collect::<Vec<_>>());
+/// ```
+pub fn par_bridge_scan<I, S, St, Map, F, R>(bound: usize, iter: I, state: S, scan: St, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    St: FnMut(&mut S, I::Item) -> Map,
+    F: FnOnce(RayonScan<I::Item, S, St>) -> R,
+{
+    par_bridge(bound, iter, |inner| f(RayonScan { inner, state, scan }))
+}
+
+/// An `Iterator` over the values derived from the elements returned by a parallel rayon
+/// pipeline, see [`par_bridge_scan`].
+pub struct RayonScan<T, S, St> {
+    inner: RayonIntoIter<T>,
+    state: S,
+    scan: St,
+}
+
+impl<T, S, St, Map> Iterator for RayonScan<T, S, St>
+where
+    St: FnMut(&mut S, T) -> Map,
+{
+    type Item = Map;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        Some((self.scan)(&mut self.state, item))
+    }
+}
+
+/// Collects the items of an indexed parallel pipeline, in completion order.
+///
+/// This is the same as `par_bridge(bound, iter, |seq_iter| seq_iter.collect())` except that
+/// the length of the pipeline is known up front, which lets collections like `Vec` allocate
+/// their final capacity once instead of growing as the items arrive.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_collect;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let result: Vec<_> = par_bridge_collect(5, data.into_par_iter().map(|num| num * 2));
+///
+/// assert_eq!(result.len(), 100);
+/// assert_eq!(result.capacity(), 100);
+/// ```
+pub fn par_bridge_collect<I, C>(bound: usize, iter: I) -> C
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    C: FromIterator<I::Item>,
+{
+    par_bridge_exact(bound, iter, Iterator::collect)
+}
+
+/// Collects the successful values of a parallel pipeline of `Result`s, in completion order, or
+/// returns the first error.
+///
+/// This mirrors collecting an iterator of `Result`s into a `Result<C, E>`, except that the
+/// values are computed in parallel. It is a shorthand for
+/// `try_par_bridge(bound, iter, |seq_iter| seq_iter.collect())`: the first error to complete
+/// stops the parallel pipeline, and the values collected so far are dropped.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_try_collect;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parsed: Result<Vec<_>, _> =
+///     par_bridge_try_collect(5, data.par_iter().map(|num| num.to_string().parse::<u32>()));
+/// assert_eq!(parsed.map(|values| values.len()), Ok(100));
+///
+/// let parsed: Result<Vec<_>, _> =
+///     par_bridge_try_collect(5, data.par_iter().map(|num| format!("-{num}").parse::<u32>()));
+/// assert!(parsed.is_err());
+/// ```
+pub fn par_bridge_try_collect<I, T, E, C>(bound: usize, iter: I) -> Result<C, E>
+where
+    I: IntoParallelIterator<Item = Result<T, E>> + Send,
+    T: Send,
+    E: Send,
+    C: FromIterator<T>,
+{
+    try_par_bridge(bound, iter, Iterator::collect)
+}
+
+/// Extends `target` with the items of the parallel pipeline, in completion order.
+///
+/// The items are added on the current thread, `target` keeps what it already holds. Clearing
+/// a `Vec` between calls, rather than collecting into a new one, reuses its capacity and
+/// keeps a hot loop from allocating.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_extend;
+/// use rayon::prelude::*;
+///
+/// let mut buffer = Vec::with_capacity(100);
+/// for round in 0u32..3 {
+///     buffer.clear();
+///     par_bridge_extend(5, (0u32..100).into_par_iter().map(|num| num + round), &mut buffer);
+///     assert_eq!(buffer.len(), 100);
+/// }
+/// ```
+pub fn par_bridge_extend<I, C>(bound: usize, iter: I, target: &mut C)
+where
+    I: IntoParallelIterator + Send,
+    C: Extend<I::Item>,
+{
+    par_bridge(bound, iter, |seq_iter| target.extend(seq_iter))
+}
+
+/// Returns the smallest item of the parallel pipeline according to `cmp`, or `None` if it is
+/// empty.
+///
+/// `cmp` is called on the current thread, it does not need to be `Send`. The items are
+/// compared in completion order, which one of several equal items is returned is arbitrary.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_min_by;
+/// use rayon::prelude::*;
+///
+/// let data = (0i32..100).collect::<Vec<_>>();
+/// let min = par_bridge_min_by(5, data.into_par_iter(), |a, b| (a - 42).abs().cmp(&(b - 42).abs()));
+///
+/// assert_eq!(min, Some(42));
+/// ```
+pub fn par_bridge_min_by<I, Cmp>(bound: usize, iter: I, cmp: Cmp) -> Option<I::Item>
+where
+    I: IntoParallelIterator + Send,
+    Cmp: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    par_bridge(bound, iter, |seq_iter| seq_iter.min_by(cmp))
+}
+
+/// Returns the greatest item of the parallel pipeline according to `cmp`, or `None` if it is
+/// empty.
+///
+/// `cmp` is called on the current thread, it does not need to be `Send`. The items are
+/// compared in completion order, which one of several equal items is returned is arbitrary.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_max_by;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).map(|num| num.to_string()).collect::<Vec<_>>();
+/// let max = par_bridge_max_by(5, data.into_par_iter(), |a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+///
+/// assert_eq!(max.as_deref(), Some("99"));
+/// ```
+pub fn par_bridge_max_by<I, Cmp>(bound: usize, iter: I, cmp: Cmp) -> Option<I::Item>
+where
+    I: IntoParallelIterator + Send,
+    Cmp: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    par_bridge(bound, iter, |seq_iter| seq_iter.max_by(cmp))
+}
+
+/// Returns the first item delivered by the parallel pipeline, or `None` if it is empty, and
+/// cancels the rest of it.
+///
+/// This suits races between equivalent computations, where any result will do and the first
+/// one to complete is the one wanted. Once the item is received the channel is dropped: the
+/// next sends of the Rayon workers fail and Rayon stops splitting the remaining work, only the
+/// items already being computed, and up to `bound` buffered ones, are wasted. This is similar
+/// to Rayon's [`ParallelIterator::find_any`], except that the item is handed over through the
+/// channel.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_first;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let first = par_bridge_first(1, data.into_par_iter().map(|num| num * 2));
+///
+/// assert!(first.is_some_and(|num| num % 2 == 0));
+/// assert_eq!(par_bridge_first(1, Vec::<u32>::new()), None);
+/// ```
+pub fn par_bridge_first<I>(bound: usize, iter: I) -> Option<I::Item>
+where
+    I: IntoParallelIterator + Send,
+{
+    par_bridge(bound, iter, |mut seq_iter| seq_iter.next())
+}
+
+/// Returns a uniform random sample of `k` items of the parallel pipeline, or all of them if
+/// there are fewer, without buffering the others.
+///
+/// The sample is drawn by reservoir sampling on the current thread as the items are received,
+/// with a random number generator seeded by `seed`. It is deterministic given the seed and the
+/// order in which the items are received, but that order depends on the scheduling of the
+/// Rayon workers: the same seed only gives the same sample when that order is fixed too, like
+/// with [`par_bridge_ordered`](crate::par_bridge_ordered). The items are in no particular
+/// order.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_sample;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..10_000).collect::<Vec<_>>();
+/// let sample = par_bridge_sample(5, 10, 42, data.into_par_iter());
+///
+/// assert_eq!(sample.len(), 10);
+/// assert!(sample.iter().all(|&num| num < 10_000));
+/// ```
+pub fn par_bridge_sample<I>(bound: usize, k: usize, seed: u64, iter: I) -> Vec<I::Item>
+where
+    I: IntoParallelIterator + Send,
+{
+    par_bridge(bound, iter, |seq_iter| {
+        let mut rng = SplitMix64(seed);
+        let mut sample = Vec::with_capacity(k);
+        for (seen, x) in seq_iter.enumerate() {
+            if sample.len() < k {
+                sample.push(x);
+            } else if let Some(slot) = sample.get_mut(rng.below(seen as u64 + 1) as usize) {
+                // The item replaces a sampled one with a probability of `k / (seen + 1)`.
+                *slot = x;
+            }
+        }
+        sample
+    })
+}
+
+/// A small, non-cryptographic random number generator.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`, with a negligible bias.
+    fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next()) * u128::from(n)) >> 64) as u64
+    }
+}
+
+/// Serializes the items of the parallel pipeline to `writer`, in completion order, and returns
+/// the writer once every item was written.
+///
+/// `serialize` is called on the current thread, one item at a time. The first error it returns
+/// stops the parallel pipeline and is returned, the items not yet written are dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// use rayon_par_bridge::par_bridge_write;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let output = par_bridge_write(5, data.into_par_iter(), Vec::new(), |writer, num| {
+///     writeln!(writer, "{num}")
+/// })
+/// .unwrap();
+///
+/// assert_eq!(output.iter().filter(|&&byte| byte == b'\n').count(), 100);
+/// ```
+pub fn par_bridge_write<I, W, Ser>(
+    bound: usize,
+    iter: I,
+    mut writer: W,
+    mut serialize: Ser,
+) -> io::Result<W>
+where
+    I: IntoParallelIterator + Send,
+    W: Write,
+    Ser: FnMut(&mut W, I::Item) -> io::Result<()>,
+{
+    par_bridge(bound, iter, |mut seq_iter| {
+        seq_iter.try_for_each(|item| serialize(&mut writer, item))
+    })?;
+    Ok(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Mutex};
+    use std::thread::{self, ThreadId};
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::par_bridge_with;
+
+    #[test]
+    fn for_each_updates_captured_state() {
+        let data = (0u64..10_000).collect::<Vec<_>>();
+        let mut sum = 0;
+        let mut count = 0;
+        par_bridge_for_each(5, data.par_iter(), |num| {
+            sum += num;
+            count += 1;
+        });
+
+        assert_eq!(sum, data.iter().sum::<u64>());
+        assert_eq!(count, 10_000);
+    }
+
+    #[test]
+    fn fold_squares() {
+        let data = (0u64..10_000).collect::<Vec<_>>();
+        let mut calls = 0;
+        let sum = par_bridge_fold(5, data.par_iter().map(|num| num * num), 0, |sum, square| {
+            calls += 1;
+            sum + square
+        });
+
+        assert_eq!(sum, data.iter().map(|num| num * num).sum::<u64>());
+        assert_eq!(calls, 10_000);
+    }
+
+    /// Records the thread it is dropped on.
+    struct Tracked<'a>(&'a Mutex<Vec<ThreadId>>);
+
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push(thread::current().id());
+        }
+    }
+
+    #[test]
+    fn buffered_items_dropped_by_the_consumer() {
+        let dropped_on = Mutex::new(Vec::new());
+        let (sent, all_sent) = mpsc::channel();
+        let result = par_bridge_with(
+            4,
+            |send| {
+                for _ in 0..4 {
+                    send.send(Tracked(&dropped_on)).map_err(drop)?;
+                }
+                sent.send(()).map_err(drop)
+            },
+            |mut seq_iter| {
+                // Abandons the iterator with three items left in the channel.
+                all_sent.recv().unwrap();
+                drop(seq_iter.next());
+            },
+        );
+
+        assert_eq!(result, Ok(()));
+        let dropped_on = dropped_on.into_inner().unwrap();
+        assert_eq!(dropped_on, [thread::current().id(); 4]);
+    }
+
+    #[test]
+    fn drain_drops_every_item_on_the_consumer() {
+        let dropped_on = Mutex::new(Vec::new());
+        let parallel_pipeline = (0..1000).into_par_iter().map(|_| Tracked(&dropped_on));
+        let count = par_bridge_drain(2, parallel_pipeline, |seq_iter| seq_iter.take(3).count());
+
+        assert_eq!(count, 3);
+
+        let dropped_on = dropped_on.into_inner().unwrap();
+        assert_eq!(dropped_on, [thread::current().id(); 1000]);
+    }
+
+    #[test]
+    fn reduce_sum_of_squares() {
+        let data = (0u64..100_000).collect::<Vec<_>>();
+        let mut partials = 0;
+        let sum = par_bridge_reduce(
+            5,
+            data.par_iter().map(|num| num * num),
+            || 0,
+            |a, b| a + b,
+            |seq_iter| seq_iter.inspect(|_| partials += 1).sum::<u64>(),
+        );
+
+        assert_eq!(sum, data.iter().map(|num| num * num).sum::<u64>());
+        assert!(partials < data.len());
+    }
+
+    #[test]
+    fn one_accumulator_per_work_unit() {
+        let data = (0u64..100_000).collect::<Vec<_>>();
+        let work_units = AtomicUsize::new(0);
+        let (accumulators, sum) = par_bridge_per_chunk(
+            5,
+            || {
+                work_units.fetch_add(1, Ordering::Relaxed);
+                0
+            },
+            |acc, num| acc + num,
+            data.par_iter(),
+            |seq_iter| seq_iter.fold((0, 0), |(count, sum), acc| (count + 1, sum + acc)),
+        );
+
+        assert_eq!(sum, data.iter().sum::<u64>());
+        assert_eq!(accumulators, work_units.into_inner());
+        assert!(accumulators < data.len() / 100);
+    }
+
+    #[test]
+    fn delivery_indices_are_gap_free() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().copied().filter(|num| num % 3 != 0);
+        let (indices, mut items): (Vec<_>, Vec<_>) =
+            par_bridge_enumerate(5, parallel_pipeline, |seq_iter| seq_iter.unzip());
+
+        assert_eq!(indices, (0..items.len()).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, data.into_iter().filter(|num| num % 3 != 0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn zip_pairs_by_arrival_position() {
+        let data = (0u32..100).collect::<Vec<_>>();
+        let pairs: Vec<_> = par_bridge_zip(5, data.par_iter(), 0..100, Iterator::collect);
+
+        assert_eq!(pairs.len(), 100);
+        assert!(pairs.iter().enumerate().all(|(position, &(_, index))| position == index));
+        let mut items: Vec<_> = pairs.into_iter().map(|(&num, _)| num).collect();
+        items.sort_unstable();
+        assert_eq!(items, data);
+
+        // The shorter side ends the pairs, on either side.
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(par_bridge_zip(5, parallel_pipeline, 0..10, Iterator::count), 10);
+        assert!(processed.load(Ordering::Relaxed) < 100_000);
+        assert_eq!(par_bridge_zip(5, data.par_iter(), 0.., Iterator::count), 100);
+    }
+
+    #[test]
+    fn tap_sees_the_delivery_order() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let mut tapped = Vec::new();
+        let delivered: Vec<_> = par_bridge_inspect(
+            5,
+            data.par_iter(),
+            |num| tapped.push(**num),
+            |seq_iter| seq_iter.copied().collect(),
+        );
+
+        assert_eq!(tapped, delivered);
+        assert_eq!(delivered.len(), 10_000);
+    }
+
+    #[test]
+    fn count_a_filtered_range() {
+        let pipeline = || (0u32..10_000).into_par_iter().filter(|num| num % 7 == 0);
+        assert_eq!(par_bridge_count(pipeline()), par_bridge(5, pipeline(), Iterator::count));
+        assert_eq!(par_bridge_count(pipeline()), 1429);
+    }
+
+    #[test]
+    fn sorted_by_key() {
+        // Multiplying by a number coprime with the length shuffles the keys.
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().map(|num| (num * 7919 % 10_000, *num));
+        let result: Vec<_> =
+            par_bridge_sorted_by_key(5, parallel_pipeline, |&(key, _)| key, Iterator::collect);
+
+        assert_eq!(result.len(), 10_000);
+        assert!(result.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn sorted_from_both_ends() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().map(|num| num * 7919 % 10_000);
+        let (front, back) = par_bridge_sorted_by_key(
+            5,
+            parallel_pipeline,
+            |&num| num,
+            |mut seq_iter| {
+                let (mut front, mut back) = (Vec::new(), Vec::new());
+                loop {
+                    match (seq_iter.next(), seq_iter.next_back()) {
+                        (Some(first), Some(last)) => {
+                            front.push(first);
+                            back.push(last);
+                        }
+                        (first, _) => break front.extend(first),
+                    }
+                }
+                (front, back)
+            },
+        );
+
+        let result: Vec<_> = front.into_iter().chain(back.into_iter().rev()).collect();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn running_sum() {
+        let data = (0u64..10_000).collect::<Vec<_>>();
+        let scanned: Vec<_> = par_bridge_scan(
+            5,
+            data.par_iter().copied(),
+            0,
+            |total, num| {
+                *total += num;
+                (num, *total)
+            },
+            |seq_iter| seq_iter.collect(),
+        );
+
+        let mut total = 0;
+        for &(num, running) in &scanned {
+            total += num;
+            assert_eq!(running, total);
+        }
+        assert_eq!(total, data.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn collect_matches_the_generic_form() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+
+        let mut generic: Vec<_> = par_bridge(5, data.par_iter(), |seq_iter| seq_iter.collect());
+        let mut collected: Vec<_> = par_bridge_collect(5, data.par_iter());
+        assert_eq!(collected.capacity(), 10_000);
+        generic.sort_unstable();
+        collected.sort_unstable();
+        assert_eq!(collected, generic);
+
+        let generic: HashSet<_> = par_bridge(5, data.par_iter(), |seq_iter| seq_iter.collect());
+        let collected: HashSet<_> = par_bridge_collect(5, data.par_iter());
+        assert_eq!(collected, generic);
+    }
+
+    #[test]
+    fn try_collect_every_value() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+
+        let collected: Result<Vec<_>, ()> = par_bridge_try_collect(5, data.par_iter().map(Ok));
+
+        let mut collected = collected.unwrap();
+        collected.sort_unstable();
+        assert_eq!(collected, data.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_collect_stops_at_the_first_error() {
+        let processed = AtomicUsize::new(0);
+        let failed = Mutex::new(Vec::new());
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().map(|num| {
+            processed.fetch_add(1, Ordering::Relaxed);
+            match num % 1000 == 999 {
+                true => {
+                    failed.lock().unwrap().push(num);
+                    Err(num)
+                }
+                false => Ok(num),
+            }
+        });
+
+        let result: Result<Vec<_>, _> = par_bridge_try_collect(5, parallel_pipeline);
+
+        // Only a worker that computed an error can report it, the first one is kept.
+        let error = result.unwrap_err();
+        assert!(failed.into_inner().unwrap().contains(&error), "{error} was never computed");
+        assert!(processed.load(Ordering::Relaxed) < 100_000);
+    }
+
+    #[test]
+    fn extend_reuses_the_buffer() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let mut buffer = Vec::with_capacity(data.len());
+        let capacity = buffer.capacity();
+
+        for _ in 0..2 {
+            buffer.clear();
+            par_bridge_extend(5, data.par_iter().copied(), &mut buffer);
+
+            assert_eq!(buffer.capacity(), capacity);
+            buffer.sort_unstable();
+            assert_eq!(buffer, data);
+        }
+    }
+
+    #[test]
+    fn extremums_match_the_sequential_ones() {
+        // The keys are all distinct, so that the extremums do not depend on the order.
+        let key = |num: &u64| num * 7919 % 10_007;
+        let data = (0u64..10_000).collect::<Vec<_>>();
+        let shuffled = || {
+            data.par_iter().map(|&num| {
+                thread::sleep(Duration::from_micros(key(&num) % 3 * 50));
+                num
+            })
+        };
+        let cmp = |a: &u64, b: &u64| key(a).cmp(&key(b));
+
+        assert_eq!(par_bridge_min_by(5, shuffled(), cmp), data.iter().copied().min_by(cmp));
+        assert_eq!(par_bridge_max_by(5, shuffled(), cmp), data.iter().copied().max_by(cmp));
+        assert_eq!(par_bridge_min_by(5, Vec::<u64>::new(), cmp), None);
+        assert_eq!(par_bridge_max_by(5, Vec::<u64>::new(), cmp), None);
+    }
+
+    #[test]
+    fn write_every_item() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let output = par_bridge_write(5, data.par_iter(), Vec::new(), |writer, num| {
+            writer.write_all(&num.to_le_bytes())
+        })
+        .unwrap();
+
+        let mut written: Vec<_> = output
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        written.sort_unstable();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn write_error_stops_the_producer() {
+        /// Fails on the tenth write.
+        struct Failing(usize);
+
+        impl Write for Failing {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0 += 1;
+                if self.0 == 10 {
+                    return Err(io::Error::other("disk full"));
+                }
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let error = par_bridge_write(1, parallel_pipeline, Failing(0), |writer, num| {
+            writer.write_all(&num.to_le_bytes())
+        })
+        .err()
+        .unwrap();
+
+        assert_eq!(error.to_string(), "disk full");
+        assert!(processed.load(Ordering::Relaxed) < 10_000);
+    }
+
+    #[test]
+    fn first_cancels_the_rest() {
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u64..1_000_000).into_par_iter().map(|num| {
+            processed.fetch_add(1, Ordering::Relaxed);
+            (0..1000).fold(num, |hash, step| hash.wrapping_mul(31).wrapping_add(step))
+        });
+
+        assert!(par_bridge_first(1, parallel_pipeline).is_some());
+        // The function only returns once the parallel pipeline stopped.
+        assert!(processed.load(Ordering::Relaxed) < 10_000);
+    }
+
+    #[test]
+    fn sample_size_and_origin() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        for (n, k) in [(0, 5), (3, 5), (5, 5), (10_000, 5), (10_000, 0)] {
+            let sample = par_bridge_sample(4, k, 42, data[..n].par_iter().copied());
+            assert_eq!(sample.len(), k.min(n), "k={k} n={n}");
+
+            let mut unique = sample.clone();
+            unique.sort_unstable();
+            unique.dedup();
+            assert_eq!(unique.len(), sample.len(), "an item was sampled twice");
+            assert!(sample.iter().all(|&num| (num as usize) < n));
+        }
+    }
+}
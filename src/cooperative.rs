@@ -0,0 +1,226 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, ThreadId};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::Yield;
+
+use crate::channel::{self, Lent, Receiver, TryRecvError};
+use crate::{ensure_returned, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but drives the parallel pipeline from a task of
+/// the current Rayon pool instead of a dedicated thread.
+///
+/// No thread is spawned: the Rayon workers waiting for room in the channel call
+/// [`rayon::yield_now`] to run other tasks in the meantime, and when `f` runs on a worker it
+/// does the same while waiting for an item. This never deadlocks, even on a pool of a single
+/// thread, but the bound is only approximate. The workers racing for the last slot can exceed
+/// it by one item each, and no worker waits while `f` itself runs a Rayon task, which can be
+/// a part of the pipeline: on a pool of a single thread the whole pipeline can end up buffered.
+/// A `bound` of zero is treated as one.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_cooperative;
+/// use rayon::prelude::*;
+///
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+/// let data = (0u32..100).collect::<Vec<_>>();
+///
+/// let sum = pool.install(|| {
+///     par_bridge_cooperative(5, data.into_par_iter(), |seq_iter| seq_iter.sum::<u32>())
+/// });
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_cooperative<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let credits = Arc::new(Credits {
+        bound: bound.max(1),
+        in_flight: AtomicUsize::new(0),
+        closed: AtomicBool::new(false),
+        consumer: thread::current().id(),
+        consumer_busy: AtomicBool::new(false),
+    });
+
+    let (send, recv) = channel::unbounded();
+    let producer_credits = credits.clone();
+    // The scope waits for the producer, which stops once the receiver is dropped with `f`.
+    rayon::in_place_scope(|s| {
+        s.spawn(move |_| {
+            iter.into_par_iter().try_for_each(|x| {
+                producer_credits.acquire();
+                send.send(x).ok()
+            });
+        });
+        let on_worker = rayon::current_thread_index().is_some();
+        let lent = Lent::default();
+        let seq_iter = Cooperative { recv, credits, on_worker, _lent: lent.clone() };
+        let result = f(RayonIntoIter::cooperative(seq_iter));
+        ensure_returned(&lent, result)
+    })
+}
+
+/// Yields the items received, running other Rayon tasks while the channel is empty.
+pub(crate) struct Cooperative<T> {
+    recv: Receiver<T>,
+    credits: Arc<Credits>,
+    /// Whether the consumer runs on a Rayon worker, that must not block on the channel.
+    on_worker: bool,
+    _lent: Lent,
+}
+
+impl<T> Cooperative<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        let item = loop {
+            match self.recv.try_recv() {
+                Ok(item) => break item,
+                Err(e) if !block => return Err(e),
+                Err(TryRecvError::Disconnected) => return Err(TryRecvError::Disconnected),
+                Err(TryRecvError::Empty) if self.on_worker => {
+                    self.credits.consumer_busy.store(true, Ordering::Release);
+                    yield_now();
+                    self.credits.consumer_busy.store(false, Ordering::Release);
+                }
+                Err(TryRecvError::Empty) => {
+                    break self.recv.recv().map_err(|_| TryRecvError::Disconnected)?
+                }
+            }
+        };
+        self.credits.in_flight.fetch_sub(1, Ordering::AcqRel);
+        Ok(item)
+    }
+}
+
+impl<T> Drop for Cooperative<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        self.credits.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Counts the items in flight against the bound.
+struct Credits {
+    bound: usize,
+    in_flight: AtomicUsize,
+    closed: AtomicBool,
+    consumer: ThreadId,
+    /// Set while the consumer runs another Rayon task, which can be a part of the pipeline
+    /// waiting for the producers: they must not wait for the consumer in the meantime.
+    consumer_busy: AtomicBool,
+}
+
+impl Credits {
+    /// Waits for the number of items in flight to drop below the bound,
+    /// running other Rayon tasks in the meantime.
+    fn acquire(&self) {
+        // Waiting on the thread running the consumer would stop it from draining the channel.
+        if thread::current().id() != self.consumer {
+            // The task run while waiting is often another part of the pipeline, that would
+            // wait and run another one in turn until the stack overflows. Only the first
+            // waiter of a thread runs other tasks, the nested ones wait for the consumer.
+            let nested = WAITING.replace(true);
+            while self.in_flight.load(Ordering::Acquire) >= self.bound
+                && !self.closed.load(Ordering::Acquire)
+                && !self.consumer_busy.load(Ordering::Acquire)
+            {
+                if nested {
+                    thread::yield_now();
+                } else {
+                    yield_now();
+                }
+            }
+            WAITING.set(nested);
+        }
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+thread_local! {
+    /// Whether a producer of the current thread is waiting for room in the channel.
+    static WAITING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs another Rayon task, or lets the OS schedule another thread if there is none.
+fn yield_now() {
+    if rayon::yield_now() != Some(Yield::Executed) {
+        thread::yield_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::par_bridge;
+
+    /// Records whether it is converted into a parallel iterator on a Rayon worker,
+    /// which tells whether the pipeline is driven from a dedicated thread.
+    struct Driven(Vec<u32>, Arc<Mutex<Option<bool>>>);
+
+    impl IntoParallelIterator for Driven {
+        type Iter = rayon::vec::IntoIter<u32>;
+        type Item = u32;
+
+        fn into_par_iter(self) -> Self::Iter {
+            *self.1.lock().unwrap() = Some(rayon::current_thread_index().is_some());
+            self.0.into_par_iter()
+        }
+    }
+
+    #[test]
+    fn single_thread_pool() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let data = (0u32..10_000).collect::<Vec<_>>();
+
+        // From a worker of the pool and from a thread outside of it.
+        let inside: Vec<_> = pool.install(|| {
+            par_bridge_cooperative(2, data.par_iter().copied(), |seq_iter| seq_iter.collect())
+        });
+        let outside: Vec<_> = pool.in_place_scope(|_| {
+            par_bridge_cooperative(2, data.par_iter().copied(), |seq_iter| seq_iter.collect())
+        });
+
+        for mut result in [inside, outside] {
+            result.sort_unstable();
+            assert_eq!(result, data);
+        }
+    }
+
+    #[test]
+    fn no_dedicated_thread() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+
+        let on_worker = Arc::new(Mutex::new(None));
+        let count = par_bridge(5, Driven(data.clone(), on_worker.clone()), Iterator::count);
+        assert_eq!(count, 1000);
+        assert_eq!(*on_worker.lock().unwrap(), Some(false));
+
+        let on_worker = Arc::new(Mutex::new(None));
+        let count = par_bridge_cooperative(5, Driven(data, on_worker.clone()), Iterator::count);
+        assert_eq!(count, 1000);
+        assert_eq!(*on_worker.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn early_drop_stops_the_producer() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let data = (0u32..100_000).collect::<Vec<_>>();
+        let count = pool.install(|| {
+            par_bridge_cooperative(1, data.into_par_iter(), |seq_iter| seq_iter.take(3).count())
+        });
+
+        assert_eq!(count, 3);
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::with_context;
+
+/// Same as [`par_bridge`](crate::par_bridge) but the consumer can take items from both ends of
+/// the buffer fed by the parallel pipeline.
+///
+/// The items are pushed at the back of a buffer of at most `bound` items, in the order the
+/// Rayon workers send them. [`RayonDeque::pop_front`] takes the oldest buffered item and
+/// [`RayonDeque::pop_back`] the newest one. When the buffer is full, the workers wait for the
+/// consumer to take an item from either end. A `bound` of zero behaves like a `bound` of one, the
+/// buffer always has room for at least one item.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_deque;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let sum = par_bridge_deque(5, data.into_par_iter(), |mut deque| {
+///     let mut sum = 0;
+///     while let Some(num) = deque.pop_back() {
+///         sum += num;
+///     }
+///     sum
+/// });
+///
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_deque<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonDeque<I::Item>) -> R,
+{
+    par_bridge_with_policy(bound, OverflowPolicy::Block, iter, f)
+}
+
+/// What the Rayon workers do with the items they produce when the consumer is lagging behind,
+/// see [`par_bridge_with_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait for the consumer to make room in the buffer, no item is lost.
+    #[default]
+    Block,
+    /// Discard the item being sent, keeping the oldest items in the buffer.
+    DropNewest,
+    /// Discard the oldest item of the buffer to make room, keeping the newest items.
+    DropOldest,
+}
+
+/// Same as [`par_bridge_deque`] but `policy` decides what happens to the items sent while
+/// the buffer is full.
+///
+/// With [`OverflowPolicy::DropNewest`] and [`OverflowPolicy::DropOldest`], the parallel
+/// pipeline never waits for the consumer and items are lost when it lags behind, which suits
+/// streams where freshness matters more than completeness, like live telemetry.
+///
+/// As with [`par_bridge_deque`], a `bound` of zero behaves like a `bound` of one: the dropping
+/// policies keep one item in the buffer rather than discarding every item.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::{par_bridge_with_policy, OverflowPolicy};
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let count = par_bridge_with_policy(5, OverflowPolicy::DropOldest, data.into_par_iter(), |deque| {
+///     deque.count()
+/// });
+///
+/// assert!((1..=100).contains(&count));
+/// ```
+pub fn par_bridge_with_policy<I, F, R>(bound: usize, policy: OverflowPolicy, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonDeque<I::Item>) -> R,
+{
+    let shared = Shared {
+        state: Mutex::new(State {
+            items: VecDeque::new(),
+            capacity: bound.max(1),
+            policy,
+            producer_done: false,
+            consumer_dropped: false,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+    };
+
+    thread::scope(|s| {
+        let producer = s.spawn(|| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                iter.into_par_iter().try_for_each(|x| shared.push(x));
+            }));
+            shared.finish();
+            result.map_err(|payload| with_context(Some(bound), payload))
+        });
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(RayonDeque { shared: &shared })));
+        let produced = producer.join();
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        if let Ok(Err(payload)) | Err(payload) = produced {
+            panic::resume_unwind(payload);
+        }
+        result
+    })
+}
+
+/// A buffer of the items returned by a parallel rayon pipeline, that can be consumed
+/// from both ends, see [`par_bridge_deque`].
+///
+/// Iterating over it pops the items from the front.
+pub struct RayonDeque<'a, T> {
+    shared: &'a Shared<T>,
+}
+
+impl<T> RayonDeque<'_, T> {
+    /// Takes the oldest buffered item, waiting for the parallel pipeline to send one if
+    /// the buffer is empty.
+    ///
+    /// Returns `None` once the buffer is empty and the parallel pipeline is done.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.pop_with(VecDeque::pop_front)
+    }
+
+    /// Takes the newest buffered item, waiting for the parallel pipeline to send one if
+    /// the buffer is empty.
+    ///
+    /// The newest item is the last one sent at the time of the call, later items are
+    /// pushed behind it. Returns `None` once the buffer is empty and the parallel pipeline
+    /// is done.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.pop_with(VecDeque::pop_back)
+    }
+
+    /// Returns the number of items currently buffered.
+    pub fn len(&self) -> usize {
+        self.shared.lock().items.len()
+    }
+
+    /// Returns `true` if no item is currently buffered,
+    /// which does not mean the parallel pipeline is done.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn pop_with(&mut self, pop: fn(&mut VecDeque<T>) -> Option<T>) -> Option<T> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(item) = pop(&mut state.items) {
+                self.shared.not_full.notify_one();
+                return Some(item);
+            }
+            if state.producer_done {
+                return None;
+            }
+            state = self.shared.not_empty.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T> Iterator for RayonDeque<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop_front()
+    }
+}
+
+impl<T> Drop for RayonDeque<'_, T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next push will fail.
+        self.shared.lock().consumer_dropped = true;
+        self.shared.not_full.notify_all();
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    /// Notified when an item is pushed or the producer is done.
+    not_empty: Condvar,
+    /// Notified when an item is popped or the consumer is dropped.
+    not_full: Condvar,
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    producer_done: bool,
+    consumer_dropped: bool,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap()
+    }
+
+    /// Makes room for the item according to the policy,
+    /// returns `None` if the consumer was dropped.
+    fn push(&self, item: T) -> Option<()> {
+        let mut state = self.lock();
+        let full = |state: &State<T>| state.items.len() >= state.capacity;
+        while state.policy == OverflowPolicy::Block && full(&state) && !state.consumer_dropped {
+            state = self.not_full.wait(state).unwrap();
+        }
+
+        if state.consumer_dropped {
+            return None;
+        }
+
+        if full(&state) {
+            match state.policy {
+                OverflowPolicy::DropOldest => drop(state.items.pop_front()),
+                // Blocking workers never find the buffer full here.
+                _ => return Some(()),
+            }
+        }
+
+        state.items.push_back(item);
+        self.not_empty.notify_one();
+        Some(())
+    }
+
+    fn finish(&self) {
+        self.lock().producer_done = true;
+        self.not_empty.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn interleaved_pops() {
+        // The items are sent in order, each one once the test grants it.
+        let (grant, permits) = mpsc::channel();
+        let permits = Mutex::new(permits);
+        let parallel_pipeline = (0u32..5).into_par_iter().with_min_len(usize::MAX).map(|num| {
+            permits.lock().unwrap().recv().unwrap();
+            num
+        });
+
+        let popped = par_bridge_deque(8, parallel_pipeline, |mut deque| {
+            // Lets `n` more items be sent and waits until `buffered` items are in the buffer.
+            let grant_and_wait = |deque: &RayonDeque<_>, n: usize, buffered: usize| {
+                (0..n).for_each(|_| grant.send(()).unwrap());
+                while deque.len() != buffered {
+                    thread::yield_now();
+                }
+            };
+
+            let mut popped = Vec::new();
+            grant_and_wait(&deque, 3, 3);
+            popped.extend([deque.pop_back(), deque.pop_front()]);
+            grant_and_wait(&deque, 2, 3);
+            popped.extend([deque.pop_back(), deque.pop_back(), deque.pop_front()]);
+            popped.extend([deque.pop_front(), deque.pop_back()]);
+            popped
+        });
+
+        assert_eq!(popped, [Some(2), Some(0), Some(4), Some(3), Some(1), None, None]);
+    }
+
+    /// Sends 0..1000 in order and returns the items left in a buffer of 4 items once they are
+    /// all sent, as if the consumer was too slow to take any of them in the meantime.
+    fn survivors(policy: OverflowPolicy) -> Vec<u32> {
+        let parallel_pipeline = (0u32..1000).into_par_iter().with_min_len(usize::MAX);
+        par_bridge_with_policy(4, policy, parallel_pipeline, |deque| {
+            while !deque.shared.lock().producer_done {
+                thread::yield_now();
+            }
+            deque.collect()
+        })
+    }
+
+    #[test]
+    fn overflow_policies() {
+        assert_eq!(survivors(OverflowPolicy::DropNewest), [0, 1, 2, 3]);
+        assert_eq!(survivors(OverflowPolicy::DropOldest), [996, 997, 998, 999]);
+
+        // A slow consumer does not lose any item when the workers wait for it.
+        let data = (0u32..200).collect::<Vec<_>>();
+        let mut result: Vec<_> =
+            par_bridge_with_policy(4, OverflowPolicy::Block, data.par_iter().copied(), |deque| {
+                deque.inspect(|_| thread::sleep(std::time::Duration::from_micros(100))).collect()
+            });
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn producer_panic_message_has_context() {
+        let payload = panic::catch_unwind(|| {
+            let parallel_pipeline = (0u32..100).into_par_iter().map(|num| {
+                if num == 42 {
+                    panic!("item {num} failed");
+                }
+                num
+            });
+            par_bridge_deque(5, parallel_pipeline, |deque| deque.count())
+        })
+        .unwrap_err();
+
+        let message = payload.downcast::<String>().unwrap();
+        assert!(message.starts_with("par_bridge producer panicked (bound=5, thread="), "{message}");
+        assert!(message.ends_with(": item 42 failed"), "{message}");
+    }
+
+    #[test]
+    fn early_drop_does_not_block_producers() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let result: Vec<_> =
+            par_bridge_deque(1, data.into_par_iter(), |deque| deque.take(3).collect());
+
+        assert_eq!(result.len(), 3);
+    }
+}
@@ -0,0 +1,202 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fmt, io, thread};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::builder::BridgePanic;
+use crate::{bridge_with, channel, with_context, RayonIntoIter};
+
+/// Why a bridge run with [`par_bridge_checked`] did not deliver every item.
+#[derive(Debug)]
+pub enum BridgeError {
+    /// The thread driving the parallel pipeline could not be spawned.
+    Spawn(io::Error),
+    /// The parallel pipeline panicked, with the panic message when the payload is a string,
+    /// prefixed like the panics resumed by [`par_bridge`](crate::par_bridge).
+    ProducerPanicked(String),
+    /// The timeout elapsed before the parallel pipeline was done.
+    Timeout,
+    /// The cancellation flag was set before the parallel pipeline was done.
+    Cancelled,
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeError::Spawn(e) => write!(f, "failed to spawn the producer thread: {e}"),
+            BridgeError::ProducerPanicked(msg) => {
+                write!(f, "the parallel pipeline panicked: {msg}")
+            }
+            BridgeError::Timeout => f.write_str("the parallel pipeline timed out"),
+            BridgeError::Cancelled => f.write_str("the parallel pipeline was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BridgeError::Spawn(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Same as [`par_bridge_with_builder`](crate::par_bridge_with_builder) but every way the
+/// stream can end early is returned as a [`BridgeError`], instead of a panic or a silently
+/// truncated stream.
+///
+/// The parallel pipeline stops sending items once `timeout`, counted from the call, elapses or
+/// once `cancel` is set. Both are checked before sending each item, so an item that takes long
+/// to compute delays the end of the stream. The consumer sees the stream end early and its
+/// result is discarded in favor of the error. Only the items that were not sent count: a
+/// pipeline that is done before the timeout elapses, or before the flag is set, succeeds.
+///
+/// A panic of the parallel pipeline is returned as [`BridgeError::ProducerPanicked`], one of
+/// `f` is still resumed on the calling thread. When the consumer stops early, by dropping the
+/// iterator, the items left unsent are not an error.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::thread;
+///
+/// use rayon_par_bridge::{par_bridge_checked, BridgeError};
+/// use rayon::prelude::*;
+///
+/// let cancel = AtomicBool::new(false);
+/// let data = (0u32..10_000).collect::<Vec<_>>();
+///
+/// let result = par_bridge_checked(
+///     thread::Builder::new(),
+///     5,
+///     data.into_par_iter(),
+///     None,
+///     Some(&cancel),
+///     |seq_iter| seq_iter.inspect(|_| cancel.store(true, Ordering::Relaxed)).count(),
+/// );
+///
+/// assert!(matches!(result, Err(BridgeError::Cancelled)));
+/// ```
+pub fn par_bridge_checked<I, F, R>(
+    builder: thread::Builder,
+    bound: usize,
+    iter: I,
+    timeout: Option<Duration>,
+    cancel: Option<&AtomicBool>,
+    f: F,
+) -> Result<R, BridgeError>
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let failure = Mutex::new(None);
+    let result = bridge_with(
+        builder,
+        channel::bounded(bound),
+        |send| {
+            let stopped = |error| {
+                failure.lock().unwrap().get_or_insert(error);
+                None
+            };
+            let produced = panic::catch_unwind(AssertUnwindSafe(|| {
+                iter.into_par_iter().try_for_each(|x| {
+                    if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+                        return stopped(BridgeError::Cancelled);
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return stopped(BridgeError::Timeout);
+                    }
+                    send.send(x).ok()
+                });
+            }));
+            if let Err(payload) = produced {
+                // The panic is the cause of the other failures, if any.
+                let panic = BridgePanic::from_payload(with_context(Some(bound), payload));
+                *failure.lock().unwrap() = Some(BridgeError::ProducerPanicked(panic.payload_msg));
+            }
+        },
+        |recv| f(RayonIntoIter::new(recv)),
+    )
+    .map_err(BridgeError::Spawn)?;
+
+    match failure.into_inner().unwrap() {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    fn checked<I: IntoParallelIterator + Send>(
+        timeout: Option<Duration>,
+        cancel: Option<&AtomicBool>,
+        iter: I,
+    ) -> Result<usize, BridgeError> {
+        par_bridge_checked(thread::Builder::new(), 5, iter, timeout, cancel, Iterator::count)
+    }
+
+    #[test]
+    fn complete_pipeline_succeeds() {
+        let cancel = AtomicBool::new(false);
+        let result = checked(Some(Duration::from_secs(60)), Some(&cancel), 0u32..1000);
+        assert_eq!(result.unwrap(), 1000);
+    }
+
+    #[test]
+    fn spawn_error() {
+        let builder = thread::Builder::new().stack_size(usize::MAX / 2);
+        let result = par_bridge_checked(builder, 5, 0u32..10, None, None, Iterator::count);
+        assert!(matches!(result, Err(BridgeError::Spawn(_))), "{result:?}");
+    }
+
+    #[test]
+    fn producer_panic() {
+        let parallel_pipeline = (0u32..1000).into_par_iter().inspect(|&num| {
+            if num == 500 {
+                panic!("corrupted record {num}");
+            }
+        });
+
+        match checked(None, None, parallel_pipeline) {
+            Err(BridgeError::ProducerPanicked(msg)) => {
+                assert!(msg.starts_with("par_bridge producer panicked (bound=5, thread="), "{msg}");
+                assert!(msg.ends_with(": corrupted record 500"), "{msg}");
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let parallel_pipeline = (0u32..1000).into_par_iter().with_max_len(1).inspect(|_| {
+            thread::sleep(Duration::from_millis(10));
+        });
+
+        let result = checked(Some(Duration::from_millis(50)), None, parallel_pipeline);
+        assert!(matches!(result, Err(BridgeError::Timeout)), "{result:?}");
+    }
+
+    #[test]
+    fn cancelled() {
+        let cancel = AtomicBool::new(false);
+        let result = par_bridge_checked(
+            thread::Builder::new(),
+            5,
+            0u32..100_000,
+            None,
+            Some(&cancel),
+            |seq_iter| seq_iter.inspect(|_| cancel.store(true, Ordering::Relaxed)).count(),
+        );
+        assert!(matches!(result, Err(BridgeError::Cancelled)), "{result:?}");
+    }
+}
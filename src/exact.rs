@@ -0,0 +1,191 @@
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator};
+
+use crate::{par_bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`] but for indexed parallel iterators, whose length is known up front
+/// and reported by the iterator given to `f`.
+///
+/// This lets `collect` preallocate and progress bars know the total. When the parallel
+/// iterator is empty, `f` receives an empty iterator and no thread is spawned.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_exact;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// par_bridge_exact(5, parallel_pipeline, |seq_iter| {
+///     assert_eq!(seq_iter.len(), 100);
+/// });
+/// ```
+pub fn par_bridge_exact<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    F: FnOnce(ExactRayonIter<I::Item>) -> R,
+{
+    let iter = iter.into_par_iter();
+    let remaining = iter.len();
+    if remaining == 0 {
+        return f(ExactRayonIter { inner: RayonIntoIter::empty(), remaining });
+    }
+    par_bridge(bound, iter, |inner| f(ExactRayonIter { inner, remaining }))
+}
+
+/// Same as [`par_bridge`] but returns `None` without calling `f` when the indexed parallel
+/// iterator is empty.
+///
+/// This tells an empty input apart from a pipeline that filtered every item out, and saves
+/// spawning the thread driving the pipeline for nothing.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_non_empty;
+/// use rayon::prelude::*;
+///
+/// let data: Vec<u32> = Vec::new();
+/// let sum = par_bridge_non_empty(5, data.into_par_iter(), |seq_iter| seq_iter.sum::<u32>());
+///
+/// assert_eq!(sum, None);
+/// ```
+pub fn par_bridge_non_empty<I, F, R>(bound: usize, iter: I, f: F) -> Option<R>
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let iter = iter.into_par_iter();
+    if iter.len() == 0 {
+        return None;
+    }
+    Some(par_bridge(bound, iter, f))
+}
+
+/// Same as [`par_bridge`] but sizes the channel to a fraction of the length of the indexed
+/// parallel iterator, read before the thread driving it is spawned.
+///
+/// The bound is `(len * frac).ceil()`, clamped between one and the length, so that `0.1`
+/// buffers up to a tenth of the items. Memory grows with the input: the channel may reserve
+/// room for all of its items up front, which for a large input can be much more than a fixed
+/// bound of a few items, for little gain once the consumer keeps up.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_frac;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..1000).collect::<Vec<_>>();
+/// let sum = par_bridge_frac(0.1, data.into_par_iter(), |seq_iter| seq_iter.sum::<u32>());
+///
+/// assert_eq!(sum, 499_500);
+/// ```
+pub fn par_bridge_frac<I, F, R>(frac: f64, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let iter = iter.into_par_iter();
+    par_bridge(frac_bound(frac, iter.len()), iter, f)
+}
+
+/// The bound holding `frac` of `len` items, at least one and at most `len`.
+fn frac_bound(frac: f64, len: usize) -> usize {
+    ((len as f64 * frac).ceil() as usize).clamp(1, len.max(1))
+}
+
+/// An `Iterator` over the elements returned by an indexed parallel rayon pipeline,
+/// knowing how many items are left.
+///
+/// The reported length assumes the parallel pipeline runs to completion. If it stops early,
+/// because it panicked for example, fewer items are yielded and the length is only an upper
+/// bound: `ExactSizeIterator` is only honored when the parallel pipeline is fully consumed.
+pub struct ExactRayonIter<T> {
+    inner: RayonIntoIter<T>,
+    remaining: usize,
+}
+
+impl<T> Iterator for ExactRayonIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        self.remaining = self.remaining.saturating_sub(1);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for ExactRayonIter<T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::{par_bridge_with_stats, SPAWNED};
+
+    #[test]
+    fn collect_preallocates() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let result = par_bridge_exact(5, data.into_par_iter(), |mut seq_iter| {
+            assert_eq!(seq_iter.len(), 1000);
+            seq_iter.next().unwrap();
+            assert_eq!(seq_iter.len(), 999);
+            seq_iter.collect::<Vec<_>>()
+        });
+
+        assert_eq!(result.len(), 999);
+        assert_eq!(result.capacity(), 999);
+    }
+
+    #[test]
+    fn empty_input_spawns_no_thread() {
+        let spawned = SPAWNED.get();
+        let empty = Vec::<u32>::new();
+
+        assert_eq!(par_bridge_non_empty(5, empty.par_iter(), Iterator::count), None);
+        assert_eq!(par_bridge_exact(5, empty.par_iter(), |seq_iter| seq_iter.len()), 0);
+        assert_eq!(par_bridge_exact(5, empty.par_iter(), Iterator::count), 0);
+        assert_eq!(SPAWNED.get(), spawned);
+
+        let filtered = par_bridge_non_empty(5, (0u32..100).into_par_iter(), |seq_iter| {
+            seq_iter.filter(|&num| num > 100).count()
+        });
+        assert_eq!(filtered, Some(0));
+        assert_eq!(SPAWNED.get(), spawned + 1);
+    }
+
+    #[test]
+    fn tenth_of_the_input_is_buffered() {
+        assert_eq!(frac_bound(0.1, 1000), 100);
+        assert_eq!(frac_bound(0.1, 5), 1);
+        assert_eq!(frac_bound(0.0, 1000), 1);
+        assert_eq!(frac_bound(f64::NAN, 1000), 1);
+        assert_eq!(frac_bound(10.0, 1000), 1000);
+        assert_eq!(frac_bound(0.5, 0), 1);
+
+        // The consumer is slow enough for the channel to fill up to the bound.
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let (_, stats) =
+            par_bridge_with_stats(frac_bound(0.1, data.len()), data.par_iter(), |seq_iter| {
+                seq_iter.for_each(|_| thread::sleep(Duration::from_micros(200)))
+            });
+        assert_eq!(stats.peak_depth, 100);
+
+        let mut result: Vec<_> = par_bridge_frac(0.1, data.par_iter().copied(), Iterator::collect);
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+}
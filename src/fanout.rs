@@ -0,0 +1,218 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, IntoIter, Lent, SharedReceiver};
+use crate::{send_all, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but the items are consumed by `consumers` threads,
+/// each one running its own clone of `f`.
+///
+/// The consumers draw their items from a single shared channel, which one receives a given
+/// item is arbitrary, and so is the order of the items within each consumer. The results are
+/// returned in the order the consumers were spawned, one per consumer.
+///
+/// With the `crossbeam` feature, the channel is a multi-consumer one that all the consumers
+/// wait on at the same time. Otherwise they take turns looking for an item, and sleep a little
+/// while the channel is empty.
+///
+/// # Panics
+///
+/// Panics if `consumers` is zero. A panic in the parallel pipeline or
+/// in any of the consumers is resumed once all of them have returned.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_fanout;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let counts = par_bridge_fanout(5, 4, data.into_par_iter(), |seq_iter| seq_iter.count());
+///
+/// assert_eq!(counts.len(), 4);
+/// assert_eq!(counts.iter().sum::<usize>(), 100);
+/// ```
+pub fn par_bridge_fanout<I, F, R>(bound: usize, consumers: usize, iter: I, f: F) -> Vec<R>
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R + Clone + Send,
+    R: Send,
+{
+    assert!(consumers != 0, "there must be at least one consumer");
+
+    let (send, recv) = channel::bounded(bound);
+    let recv = SharedReceiver::new(recv);
+    thread::scope(|s| {
+        let producer = s.spawn(move || {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                send_all(iter.into_par_iter(), &send);
+            }))
+        });
+
+        let consumers: Vec<_> = (0..consumers)
+            .map(|_| {
+                let recv = recv.clone();
+                let f = f.clone();
+                s.spawn(move || f(RayonIntoIter::shared(recv)))
+            })
+            .collect();
+        // Only the consumers must keep the channel open.
+        drop(recv);
+
+        let results: Vec<_> = consumers.into_iter().map(|consumer| consumer.join()).collect();
+        if let Ok(Err(payload)) | Err(payload) = producer.join() {
+            panic::resume_unwind(payload);
+        }
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|payload| panic::resume_unwind(payload)))
+            .collect()
+    })
+}
+
+/// Same as [`par_bridge_fanout`] but each item is sent to the consumer picked by `route`, so
+/// that a given consumer sees every item of its partition and nothing else.
+///
+/// The item goes to the consumer at index `route(&item) % partitions`, each consumer has its
+/// own channel of `bound` items and runs its own clone of `f` on a dedicated thread. This
+/// suits a downstream resource per partition, like a file per shard, that must not be shared
+/// between threads. The results are returned in partition order.
+///
+/// The items routed to a consumer that stopped early are dropped, the parallel pipeline stops
+/// once every consumer did.
+///
+/// # Panics
+///
+/// Panics if `partitions` is zero. A panic in the parallel pipeline or
+/// in any of the consumers is resumed once all of them have returned.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_route;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let sums = par_bridge_route(5, 2, |&num| num as usize, data.into_par_iter(), |seq_iter| {
+///     seq_iter.sum::<u32>()
+/// });
+///
+/// assert_eq!(sums, [2450, 2500]);
+/// ```
+pub fn par_bridge_route<I, Route, F, R>(
+    bound: usize,
+    partitions: usize,
+    route: Route,
+    iter: I,
+    f: F,
+) -> Vec<R>
+where
+    I: IntoParallelIterator + Send,
+    Route: Fn(&I::Item) -> usize + Sync + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R + Clone + Send,
+    R: Send,
+{
+    assert!(partitions != 0, "there must be at least one partition");
+
+    let (senders, receivers): (Vec<_>, Vec<_>) =
+        (0..partitions).map(|_| channel::bounded(bound)).unzip();
+    thread::scope(|s| {
+        let producer = s.spawn(move || {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                let closed: Vec<_> = (0..partitions).map(|_| AtomicBool::new(false)).collect();
+                let open = AtomicUsize::new(partitions);
+                iter.into_par_iter().try_for_each(|item| {
+                    let partition = route(&item) % partitions;
+                    if senders[partition].send(item).is_err()
+                        && !closed[partition].swap(true, Ordering::Relaxed)
+                    {
+                        open.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    (open.load(Ordering::Relaxed) != 0).then_some(())
+                });
+            }))
+        });
+
+        let consumers: Vec<_> = receivers
+            .into_iter()
+            .map(|recv| {
+                let f = f.clone();
+                s.spawn(move || {
+                    let lent = Lent::default();
+                    f(RayonIntoIter::new(IntoIter::new(recv, &lent)))
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = consumers.into_iter().map(|consumer| consumer.join()).collect();
+        if let Ok(Err(payload)) | Err(payload) = producer.join() {
+            panic::resume_unwind(payload);
+        }
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|payload| panic::resume_unwind(payload)))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn consumers_partition_the_input() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let outputs = par_bridge_fanout(5, 4, data.par_iter().copied(), |seq_iter| {
+            seq_iter.collect::<Vec<_>>()
+        });
+
+        assert_eq!(outputs.len(), 4);
+        let mut items: Vec<_> = outputs.into_iter().flatten().collect();
+        items.sort_unstable();
+        assert_eq!(items, data);
+    }
+
+    #[test]
+    fn items_land_in_their_partition() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let outputs = par_bridge_route(
+            5,
+            3,
+            |&num| num as usize * 7,
+            data.par_iter().copied(),
+            |seq_iter| seq_iter.collect::<Vec<_>>(),
+        );
+
+        assert_eq!(outputs.len(), 3);
+        for (partition, items) in outputs.iter().enumerate() {
+            assert!(items.iter().all(|&num| num as usize * 7 % 3 == partition));
+        }
+        let mut items: Vec<_> = outputs.into_iter().flatten().collect();
+        items.sort_unstable();
+        assert_eq!(items, data);
+    }
+
+    #[test]
+    fn route_stops_once_every_consumer_stopped() {
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let counts = par_bridge_route(
+            1,
+            2,
+            |&num| num as usize,
+            parallel_pipeline,
+            |seq_iter| seq_iter.take(5).count(),
+        );
+
+        assert_eq!(counts, [5, 5]);
+        assert!(processed.load(Ordering::Relaxed) < 10_000);
+    }
+}
@@ -0,0 +1,84 @@
+use std::iter::FusedIterator;
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::{par_bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`] but for pipelines of collections, the consumer receives the items
+/// of each collection one by one.
+///
+/// The collections are sent through the channel as they are and flattened on the consumer
+/// side, the items of a given collection are yielded in a row and in their order. Empty
+/// collections are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_flat;
+/// use rayon::prelude::*;
+///
+/// let data = (0usize..10).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|len| vec![len; len]);
+///
+/// let count = par_bridge_flat(5, parallel_pipeline, |seq_iter| seq_iter.count());
+/// assert_eq!(count, 45);
+/// ```
+pub fn par_bridge_flat<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Item: IntoIterator,
+    F: FnOnce(RayonFlatIter<I::Item>) -> R,
+{
+    par_bridge(bound, iter, |inner| f(RayonFlatIter { inner, current: None }))
+}
+
+/// An `Iterator` over the items of the collections returned by a parallel rayon pipeline,
+/// see [`par_bridge_flat`].
+pub struct RayonFlatIter<T: IntoIterator> {
+    inner: RayonIntoIter<T>,
+    current: Option<T::IntoIter>,
+}
+
+impl<T: IntoIterator> Iterator for RayonFlatIter<T> {
+    type Item = T::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(item);
+            }
+            self.current = Some(self.inner.next()?.into_iter());
+        }
+    }
+}
+
+impl<T: IntoIterator> FusedIterator for RayonFlatIter<T> {}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn collections_are_yielded_in_a_row() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let parallel_pipeline =
+            data.par_iter().map(|&num| (0..num % 5).map(|i| (num, i)).collect::<Vec<_>>());
+        let mut result: Vec<_> =
+            par_bridge_flat(5, parallel_pipeline, |seq_iter| seq_iter.collect());
+
+        // Every collection is contiguous and in order, whatever the order of the collections.
+        let mut expected: Vec<_> =
+            data.iter().flat_map(|&num| (0..num % 5).map(move |i| (num, i))).collect();
+        assert_eq!(result.len(), expected.len());
+        for window in result.windows(2) {
+            let [(a, i), (b, j)] = [window[0], window[1]];
+            assert!(a != b || j == i + 1, "{window:?}");
+            assert!(a == b || j == 0, "{window:?}");
+        }
+        result.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(result, expected);
+    }
+}
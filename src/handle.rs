@@ -0,0 +1,470 @@
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, Receiver, TryRecvError};
+use crate::{send_all, RayonIntoIter};
+
+/// How long a Rayon worker of [`par_bridge_raw`] first waits for room in a full channel before
+/// trying again.
+const MIN_BACKOFF: Duration = Duration::from_micros(50);
+/// The longest a Rayon worker of [`par_bridge_raw`] waits for room before trying again.
+const MAX_BACKOFF: Duration = Duration::from_millis(1);
+/// How long the iterator of [`par_bridge_handle`] waits for an item before letting its guard
+/// take the receiver away.
+const RECV_STEP: Duration = Duration::from_millis(1);
+
+/// Same as [`par_bridge`](crate::par_bridge) but returns the sequential iterator instead of
+/// lending it to a closure, along with a guard that owns the thread driving the pipeline.
+///
+/// The iterator can be stored in a struct field or returned from a function. As the producer
+/// runs on a detached thread, the parallel iterator must be `'static`. Dropping the
+/// [`BridgeGuard`] stops the parallel pipeline, even if the iterator was not fully consumed
+/// or is still alive, and waits for the producer thread to exit.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_handle;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let (seq_iter, guard) = par_bridge_handle(5, data.into_par_iter().map(|num| num * 2));
+///
+/// let first: Vec<_> = seq_iter.take(3).collect();
+/// assert_eq!(first.len(), 3);
+///
+/// drop(guard);
+/// ```
+pub fn par_bridge_handle<I>(bound: usize, iter: I) -> (RayonIntoIter<I::Item>, BridgeGuard)
+where
+    I: IntoParallelIterator + Send + 'static,
+{
+    let (send, recv) = channel::bounded(bound);
+    let recv =
+        Arc::new(Guarded { recv: Mutex::new(Some(recv)), disconnecting: AtomicBool::new(false) });
+    let (exiting, exited) = mpsc::channel();
+    let producer = thread::spawn(move || {
+        // Dropped when the thread exits, even if it panics.
+        let _exiting = exiting;
+        send_all(iter.into_par_iter(), &send);
+    });
+
+    let guarded = recv.clone();
+    let disconnect = Box::new(move || guarded.disconnect());
+    let guard = BridgeGuard { disconnect: Some(disconnect), producer: Some(producer), exited };
+    (RayonIntoIter::guarded(recv), guard)
+}
+
+/// The receiver of [`par_bridge_handle`], that its guard drops to stop the parallel pipeline.
+pub(crate) struct Guarded<T> {
+    recv: Mutex<Option<Receiver<T>>>,
+    /// Set by the guard before it takes the receiver, so that a consumer waiting for an item
+    /// gives up the lock for good instead of taking it again.
+    disconnecting: AtomicBool,
+}
+
+impl<T> Guarded<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.lock().unwrap().as_ref().is_none_or(Receiver::is_disconnected)
+    }
+
+    /// Receives an item, the lock is never held for more than a few milliseconds as the guard
+    /// may be dropped from another thread while the consumer waits.
+    pub(crate) fn recv(&self, block: bool) -> Result<T, TryRecvError> {
+        loop {
+            if self.disconnecting.load(Ordering::Acquire) {
+                return Err(TryRecvError::Disconnected);
+            }
+            let Some(recv) = &*self.recv.lock().unwrap() else {
+                return Err(TryRecvError::Disconnected);
+            };
+            if !block {
+                return recv.try_recv();
+            }
+            match recv.recv_timeout(RECV_STEP) {
+                Ok(x) => return Ok(x),
+                Err(RecvTimeoutError::Disconnected) => return Err(TryRecvError::Disconnected),
+                Err(RecvTimeoutError::Timeout) => (),
+            }
+        }
+    }
+
+    fn disconnect(&self) {
+        self.disconnecting.store(true, Ordering::Release);
+        drop(self.recv.lock().unwrap().take());
+    }
+}
+
+/// Transforms a `'static` Rayon parallel iterator into a sequential iterator that can be used
+/// directly, in a `for` loop for example.
+///
+/// This is the same as [`par_bridge_handle`] with the guard stored in the iterator: dropping
+/// the iterator stops the parallel pipeline and waits for the thread driving it to exit, even
+/// if it was not fully consumed. As that thread is detached from the current scope, the
+/// parallel iterator must be `'static`, use [`par_bridge`](crate::par_bridge) to bridge
+/// pipelines that borrow from the caller.
+///
+/// The iterator is `Send` when the items are, it can be moved to another thread or into a
+/// Rayon task to be consumed there.
+///
+/// # Panics
+///
+/// Dropping the iterator resumes the panic of the parallel pipeline, if any,
+/// unless the current thread is already panicking.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_into_iter;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let mut sum = 0;
+/// for num in par_bridge_into_iter(5, data.into_par_iter().map(|num| num * 2)) {
+///     sum += num;
+/// }
+///
+/// assert_eq!(sum, 9900);
+/// ```
+pub fn par_bridge_into_iter<I>(bound: usize, iter: I) -> RayonIntoIter<I::Item>
+where
+    I: IntoParallelIterator + Send + 'static,
+{
+    let (seq_iter, guard) = par_bridge_handle(bound, iter);
+    seq_iter.with_guard(guard)
+}
+
+/// Same as [`par_bridge_handle`] but returns the receiving end of the underlying channel, a
+/// standard [`mpsc::Receiver`], instead of wrapping it in an iterator.
+///
+/// The receiver can be polled with `try_recv`, waited on with `recv_timeout`, or merged with
+/// other channels in a custom event loop. It holds at most `bound` items and ends, with a
+/// disconnection error, once the parallel pipeline is done.
+///
+/// As the guard cannot drop a receiver it does not own, the Rayon workers finding the channel
+/// full do not block on it: they wait for room in short sleeps, of up to a millisecond, and
+/// give up as soon as the guard is dropped. This makes a refill slightly slower than with
+/// [`par_bridge_handle`], and each item of a `bound` of zero waits for one of these sleeps.
+///
+/// # Drop order
+///
+/// - Dropping the [`BridgeGuard`] stops the parallel pipeline and waits for the items being
+///   computed, then for the producer thread to exit. The items already in the channel can
+///   still be received, after which the receiver reports a disconnection.
+/// - Dropping the receiver first makes the next sends fail, the parallel pipeline stops on its
+///   own and dropping the guard only joins the producer thread.
+/// - Dropping the guard blocks the current thread, do not drop it from code that the items
+///   being computed wait for.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::mpsc::RecvTimeoutError;
+/// use std::time::Duration;
+///
+/// use rayon_par_bridge::par_bridge_raw;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let (recv, guard) = par_bridge_raw(5, data.into_par_iter().map(|num| num * 2));
+///
+/// let mut sum = 0;
+/// loop {
+///     match recv.recv_timeout(Duration::from_secs(10)) {
+///         Ok(num) => sum += num,
+///         Err(RecvTimeoutError::Disconnected) => break,
+///         Err(RecvTimeoutError::Timeout) => panic!("the pipeline is stuck"),
+///     }
+/// }
+///
+/// assert_eq!(sum, 9900);
+/// drop(guard);
+/// ```
+pub fn par_bridge_raw<I>(bound: usize, iter: I) -> (mpsc::Receiver<I::Item>, BridgeGuard)
+where
+    I: IntoParallelIterator + Send + 'static,
+{
+    let (send, recv) = mpsc::sync_channel(bound);
+    let stop = Arc::new(Stop::default());
+    let (exiting, exited) = mpsc::channel();
+    let producer_stop = stop.clone();
+    let producer = thread::spawn(move || {
+        // Dropped when the thread exits, even if it panics.
+        let _exiting = exiting;
+        iter.into_par_iter().try_for_each(|x| producer_stop.send(&send, x));
+    });
+
+    let disconnect = Box::new(move || stop.stop());
+    let guard = BridgeGuard { disconnect: Some(disconnect), producer: Some(producer), exited };
+    (recv, guard)
+}
+
+/// Lets the guard of [`par_bridge_raw`] interrupt the Rayon workers waiting for room.
+#[derive(Default)]
+struct Stop {
+    stopped: AtomicBool,
+    lock: Mutex<()>,
+    /// Notified when the guard is dropped.
+    cond: Condvar,
+}
+
+impl Stop {
+    /// Sends the item, returns `None` if the receiver was dropped or the pipeline stopped.
+    fn send<T>(&self, send: &SyncSender<T>, mut x: T) -> Option<()> {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            if self.stopped.load(Ordering::Acquire) {
+                return None;
+            }
+            match send.try_send(x) {
+                Ok(()) => return Some(()),
+                Err(TrySendError::Disconnected(_)) => return None,
+                Err(TrySendError::Full(item)) => x = item,
+            }
+
+            let lock = self.lock.lock().unwrap();
+            if !self.stopped.load(Ordering::Acquire) {
+                drop(self.cond.wait_timeout(lock, backoff).unwrap());
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    fn stop(&self) {
+        let _lock = self.lock.lock().unwrap();
+        self.stopped.store(true, Ordering::Release);
+        self.cond.notify_all();
+    }
+}
+
+/// Stops and joins the thread driving a parallel pipeline when dropped,
+/// see [`par_bridge_handle`].
+///
+/// # Panics
+///
+/// Dropping the guard resumes the panic of the parallel pipeline, if any,
+/// unless the current thread is already panicking.
+pub struct BridgeGuard {
+    /// Drops the receiver, erasing its item type.
+    disconnect: Option<Box<dyn FnOnce() + Send>>,
+    producer: Option<JoinHandle<()>>,
+    /// Disconnected once the producer thread exits.
+    exited: mpsc::Receiver<()>,
+}
+
+/// Whether the thread driving the pipeline exited in time, see [`BridgeGuard::join_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinStatus {
+    /// The thread exited and was joined.
+    Joined,
+    /// The thread was still running after the timeout and was detached.
+    TimedOut,
+}
+
+impl BridgeGuard {
+    /// Stops the parallel pipeline like dropping the guard does, but waits at most `timeout`
+    /// for the thread driving it to exit.
+    ///
+    /// A thread cannot be killed: when a Rayon worker is stuck on an item, the thread is left
+    /// running detached and exits on its own once the parallel pipeline returns, which may be
+    /// never if the item is deadlocked.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of the parallel pipeline, if any, when the thread exited in time.
+    pub fn join_timeout(mut self, timeout: Duration) -> JoinStatus {
+        self.disconnect();
+        let producer = self.producer.take().unwrap();
+        match self.exited.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => JoinStatus::TimedOut,
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => {
+                if let Err(payload) = producer.join() {
+                    panic::resume_unwind(payload);
+                }
+                JoinStatus::Joined
+            }
+        }
+    }
+
+    /// Drops the receiver so that the producers blocked on a full channel can exit.
+    fn disconnect(&mut self) {
+        if let Some(disconnect) = self.disconnect.take() {
+            disconnect();
+        }
+    }
+}
+
+impl Drop for BridgeGuard {
+    fn drop(&mut self) {
+        self.disconnect();
+        if let Some(Err(payload)) = self.producer.take().map(JoinHandle::join) {
+            if !thread::panicking() {
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Instant;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    /// Raises the flag when dropped.
+    struct SetOnDrop(Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    struct Pipeline {
+        items: RayonIntoIter<u32>,
+        _guard: BridgeGuard,
+    }
+
+    #[test]
+    fn stored_in_a_struct() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let flag = SetOnDrop(finished.clone());
+        let parallel_pipeline = (0u32..100_000).into_par_iter().map(move |num| {
+            let _flag = &flag;
+            num
+        });
+
+        let (items, _guard) = par_bridge_handle(1, parallel_pipeline);
+        let mut pipeline = Pipeline { items, _guard };
+        assert_eq!(pipeline.items.by_ref().take(3).count(), 3);
+        assert!(!finished.load(Ordering::SeqCst));
+
+        drop(pipeline);
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn for_loop_with_early_break() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let flag = SetOnDrop(finished.clone());
+        let parallel_pipeline = (0u32..100_000).into_par_iter().map(move |num| {
+            let _flag = &flag;
+            num
+        });
+
+        let mut seen = 0;
+        for _ in par_bridge_into_iter(1, parallel_pipeline) {
+            seen += 1;
+            if seen == 3 {
+                break;
+            }
+        }
+
+        assert_eq!(seen, 3);
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn drained_on_another_thread() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let seq_iter = par_bridge_into_iter(5, (0u32..1000).into_par_iter());
+        assert_send(&seq_iter);
+
+        let mut result: Vec<_> = thread::spawn(move || seq_iter.collect()).join().unwrap();
+        result.sort_unstable();
+        assert_eq!(result, (0u32..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn join_timeout_detaches_a_stuck_producer() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let flag = SetOnDrop(finished.clone());
+        let parallel_pipeline = (0u32..4).into_par_iter().map(move |num| {
+            let _flag = &flag;
+            thread::sleep(Duration::from_millis(500));
+            num
+        });
+
+        let (_seq_iter, guard) = par_bridge_handle(1, parallel_pipeline);
+        let start = Instant::now();
+        assert_eq!(guard.join_timeout(Duration::from_millis(10)), JoinStatus::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(400));
+
+        // The detached thread exits on its own once the sleeping items are done.
+        while !finished.load(Ordering::SeqCst) {
+            assert!(start.elapsed() < Duration::from_secs(30), "the producer never exited");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let (_seq_iter, guard) = par_bridge_handle(1, (0u32..4).into_par_iter());
+        assert_eq!(guard.join_timeout(Duration::from_secs(30)), JoinStatus::Joined);
+    }
+
+    #[test]
+    fn join_timeout_while_next_waits_on_another_thread() {
+        let parallel_pipeline = (0u32..4).into_par_iter().map(|num| {
+            thread::sleep(Duration::from_millis(500));
+            num
+        });
+
+        let (mut seq_iter, guard) = par_bridge_handle(1, parallel_pipeline);
+        let consumer = thread::spawn(move || seq_iter.next());
+        // Lets the consumer wait for the first item.
+        thread::sleep(Duration::from_millis(50));
+
+        let start = Instant::now();
+        assert_eq!(guard.join_timeout(Duration::from_millis(10)), JoinStatus::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(400));
+        assert_eq!(consumer.join().unwrap(), None);
+    }
+
+    #[test]
+    fn guard_dropped_before_the_iterator() {
+        let data = (0u32..100_000).collect::<Vec<_>>();
+        let (mut items, guard) = par_bridge_handle(1, data.into_par_iter());
+        assert!(items.next().is_some());
+
+        drop(guard);
+        assert_eq!(items.next(), None);
+    }
+
+    #[test]
+    fn raw_receiver_is_fully_drained() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let (recv, guard) = par_bridge_raw(5, data.clone().into_par_iter());
+
+        let mut result: Vec<_> = recv.iter().collect();
+        drop(guard);
+
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn dropping_the_raw_guard_stops_the_producer() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let flag = SetOnDrop(finished.clone());
+        let parallel_pipeline = (0u32..100_000).into_par_iter().map(move |num| {
+            let _flag = &flag;
+            num
+        });
+
+        let (recv, guard) = par_bridge_raw(4, parallel_pipeline);
+        assert!(recv.recv().is_ok());
+
+        // The receiver is alive, the workers waiting on the full channel must still give up.
+        drop(guard);
+        assert!(finished.load(Ordering::SeqCst));
+        assert!(recv.iter().count() <= 4);
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+use rayon::iter::{Either, IntoParallelIterator, ParallelIterator};
+
+use crate::{bridge, RayonIntoIter, TryNextError};
+
+/// Same as [`par_bridge`](crate::par_bridge) but bridges two parallel iterators of different
+/// types at once, and alternates between their items.
+///
+/// Each pipeline is driven from its own thread and sends its items through its own channel of
+/// `bound` items. The iterator given to `f` takes an item from each channel in turn when both
+/// have one ready, and from whichever has one otherwise, so that a slow pipeline does not hold
+/// up the other one. Once a pipeline is done, the items of the other one are yielded in a row.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::iter::Either;
+/// use rayon_par_bridge::par_bridge_interleave;
+/// use rayon::prelude::*;
+///
+/// let numbers = (0u32..50).collect::<Vec<_>>();
+/// let words = (0u32..50).map(|num| num.to_string()).collect::<Vec<_>>();
+///
+/// let (sum, len) = par_bridge_interleave(5, numbers, words, |seq_iter| {
+///     seq_iter.fold((0, 0), |(sum, len), item| match item {
+///         Either::Left(num) => (sum + num, len),
+///         Either::Right(word) => (sum, len + word.len()),
+///     })
+/// });
+///
+/// assert_eq!((sum, len), (1225, 90));
+/// ```
+pub fn par_bridge_interleave<IA, IB, F, R>(bound: usize, iter_a: IA, iter_b: IB, f: F) -> R
+where
+    IA: IntoParallelIterator + Send,
+    IB: IntoParallelIterator + Send,
+    F: FnOnce(RayonInterleave<IA::Item, IB::Item>) -> R,
+{
+    // Rung after each item sent, so that the consumer can wait for either channel.
+    let (ring_a, bell) = mpsc::sync_channel(1);
+    let ring_b = ring_a.clone();
+    bridge(
+        bound,
+        move |send| send_ringing(iter_a, |x| send.send(x).is_ok(), ring_a),
+        |a| {
+            bridge(
+                bound,
+                move |send| send_ringing(iter_b, |x| send.send(x).is_ok(), ring_b),
+                |b| f(RayonInterleave { a, b, b_turn: false, bell }),
+            )
+        },
+    )
+}
+
+/// Sends the items of the parallel pipeline and rings the bell after each one.
+fn send_ringing<I, S>(iter: I, send: S, ring: SyncSender<()>)
+where
+    I: IntoParallelIterator,
+    S: Fn(I::Item) -> bool + Sync + Send,
+{
+    iter.into_par_iter().try_for_each(|x| {
+        if !send(x) {
+            return None;
+        }
+        // A full bell was already rung and not heard yet.
+        let _ = ring.try_send(());
+        Some(())
+    });
+}
+
+/// An `Iterator` alternating between the elements returned by two parallel rayon pipelines,
+/// see [`par_bridge_interleave`].
+pub struct RayonInterleave<A, B> {
+    a: RayonIntoIter<A>,
+    b: RayonIntoIter<B>,
+    /// Whether the next item should be taken from `b` if it has one ready.
+    b_turn: bool,
+    bell: mpsc::Receiver<()>,
+}
+
+impl<A, B> Iterator for RayonInterleave<A, B> {
+    type Item = Either<A, B>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Tries the side whose turn it is, then the other one.
+            for _ in 0..2 {
+                let item = match self.b_turn {
+                    false => self.a.try_next().map(Either::Left),
+                    true => self.b.try_next().map(Either::Right),
+                };
+                self.b_turn = !self.b_turn;
+                match item {
+                    Ok(item) => return Some(item),
+                    Err(TryNextError::Empty | TryNextError::Disconnected) => (),
+                }
+            }
+
+            if self.a.is_done() && self.b.is_done() {
+                return None;
+            }
+            if self.bell.recv().is_err() {
+                // Both pipelines are done, their channels are about to be disconnected.
+                thread::yield_now();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn types_alternate_while_both_are_ready() {
+        let numbers = (0u32..50).collect::<Vec<_>>();
+        let words = (0u32..50).map(|num| num.to_string()).collect::<Vec<_>>();
+
+        let items: Vec<_> = par_bridge_interleave(50, numbers, words, |mut seq_iter| {
+            // Every item is buffered before the first one is taken.
+            let start = Instant::now();
+            while !(seq_iter.a.producer_finished() && seq_iter.b.producer_finished()) {
+                assert!(start.elapsed().as_secs() < 30, "the producers never finished");
+                thread::yield_now();
+            }
+            seq_iter.by_ref().collect()
+        });
+
+        assert_eq!(items.len(), 100);
+        for (position, item) in items.iter().enumerate() {
+            assert_eq!(item.is_left(), position % 2 == 0, "{position}");
+        }
+        let mut numbers: Vec<_> = items.iter().filter_map(|item| item.clone().left()).collect();
+        numbers.sort_unstable();
+        assert_eq!(numbers, (0..50).collect::<Vec<_>>());
+        assert_eq!(items.iter().filter(|item| item.is_right()).count(), 50);
+    }
+
+    #[test]
+    fn the_longer_stream_continues_alone() {
+        let (short, long) = ((0u32..5).collect::<Vec<_>>(), (0u64..1000).collect::<Vec<_>>());
+        let (lefts, rights) = par_bridge_interleave(4, short, long, |seq_iter| {
+            seq_iter.fold((0, 0), |(lefts, rights), item| match item {
+                Either::Left(_) => (lefts + 1, rights),
+                Either::Right(_) => (lefts, rights + 1),
+            })
+        });
+
+        assert_eq!((lefts, rights), (5, 1000));
+    }
+}
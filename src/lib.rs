@@ -1,9 +1,118 @@
 //! This crate provides an elegant solution for integrating Rayon's parallel processing
 //! power with the traditional sequential iterator pattern in Rust.
 
-use std::sync::mpsc::{self, IntoIter};
+use std::any::Any;
+use std::iter::FusedIterator;
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use std::{fmt, io, thread, vec};
 
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::ThreadPool;
+
+pub use self::adaptive::par_bridge_adaptive;
+use self::adaptive::Adaptive;
+use self::batched::Unpack;
+pub use self::batched::{par_bridge_batched, par_bridge_copy};
+pub use self::builder::{BridgePanic, PanicPolicy, ParBridge};
+pub use self::cancel::{
+    par_bridge_cancellable, par_bridge_cancellable_drain, par_bridge_pausable, par_bridge_take,
+    PauseHandle,
+};
+pub use self::chain::{par_bridge_stages, SeqStage};
+use self::channel::{IntoIter, Lent, Receiver, Sender, SharedReceiver, TryRecvError};
+pub use self::chunks::{par_bridge_chunks, par_bridge_pairs, RayonChunks, RayonPairs};
+pub use self::consumers::{
+    par_bridge_collect, par_bridge_count, par_bridge_drain, par_bridge_enumerate,
+    par_bridge_extend, par_bridge_first, par_bridge_fold, par_bridge_for_each, par_bridge_inspect,
+    par_bridge_max_by, par_bridge_min_by, par_bridge_per_chunk, par_bridge_reduce,
+    par_bridge_sample, par_bridge_scan, par_bridge_sorted_by_key, par_bridge_try_collect,
+    par_bridge_write, par_bridge_zip, RayonScan,
+};
+pub use self::cooperative::par_bridge_cooperative;
+use self::cooperative::Cooperative;
+pub use self::deque::{par_bridge_deque, par_bridge_with_policy, OverflowPolicy, RayonDeque};
+pub use self::error::{par_bridge_checked, BridgeError};
+pub use self::exact::{par_bridge_exact, par_bridge_frac, par_bridge_non_empty, ExactRayonIter};
+pub use self::fanout::{par_bridge_fanout, par_bridge_route};
+pub use self::flat::{par_bridge_flat, RayonFlatIter};
+use self::handle::Guarded;
+pub use self::handle::{
+    par_bridge_handle, par_bridge_into_iter, par_bridge_raw, BridgeGuard, JoinStatus,
+};
+pub use self::interleave::{par_bridge_interleave, RayonInterleave};
+pub use self::local::{par_bridge_local, LocalRayonIter};
+pub use self::map::par_bridge_map;
+pub use self::ordered::{
+    par_bridge_ordered, par_bridge_ordered_dedup_by_key, par_bridge_ordered_group_by,
+    par_bridge_windowed, DedupRayonIter, OrderedRayonIter, RayonGroups,
+};
+use self::ordered::{Reorder, Windowed};
+pub use self::pool::BridgePool;
+pub use self::priority::par_bridge_priority;
+pub use self::producer::{par_bridge_with, BridgeSender};
+pub use self::rate::{par_bridge_rate_limited, RateLimitedRayonIter};
+pub use self::rendezvous::par_bridge_rendezvous;
+use self::rendezvous::SlotReceiver;
+pub use self::retry::par_bridge_retry;
+pub use self::rolling::{par_bridge_window, RayonWindowIter};
+pub use self::scoped::par_bridge_scope;
+pub use self::side::{par_bridge_with_side, BridgeOut};
+#[cfg(feature = "spill")]
+pub use self::spill::{par_bridge_ordered_spill, SpillRayonIter};
+pub use self::stages::{par_bridge_staged, par_bridge_then_par, par_pipe};
+use self::stats::Counters;
+pub use self::stats::{
+    par_bridge_counted, par_bridge_timed, par_bridge_with_progress, par_bridge_with_stats,
+    BridgeStats, TimedRayonIter,
+};
+#[cfg(feature = "futures")]
+pub use self::stream::{par_stream, ParStream};
+use self::tunable::Tunable;
+pub use self::tunable::{par_bridge_tunable, BoundHandle};
+pub use self::weighted::par_bridge_weighted;
+use self::weighted::Weighted;
+
+mod adaptive;
+mod batched;
+mod builder;
+mod cancel;
+mod chain;
+mod channel;
+mod chunks;
+mod consumers;
+mod cooperative;
+mod deque;
+mod error;
+mod exact;
+mod fanout;
+mod flat;
+mod handle;
+mod interleave;
+mod local;
+mod map;
+mod ordered;
+mod pool;
+mod priority;
+mod producer;
+mod rate;
+mod rendezvous;
+mod retry;
+mod rolling;
+mod scoped;
+mod side;
+#[cfg(any(feature = "spill", test))]
+mod spill;
+mod stages;
+mod stats;
+#[cfg(any(feature = "futures", test))]
+mod stream;
+mod trace;
+mod tunable;
+mod weighted;
 
 /// Transforms a Rayon parallel iterator into a sequentially processed iterator.
 ///
@@ -37,54 +146,1820 @@ use rayon::iter::{IntoParallelIterator, ParallelIterator};
 ///
 /// # Parameters
 /// - `bound`: The size of the internal buffer used to transition items from the parallel
-/// pipeline to the sequential iterator. Larger values allow more parallel processing but
-/// increase memory usage.
-/// - `iter`: The Rayon parallel iterator to be consumed.
+///   pipeline to the sequential iterator. Larger values allow more parallel processing but
+///   increase memory usage. With a `bound` of zero, every item is handed over directly from a
+///   Rayon worker to the consumer: the workers wait for the consumer to take their item before
+///   computing another one, which keeps at most one item per worker in flight but also limits
+///   the pipeline to the pace of the consumer. Use [`par_bridge_nz`] to rule it out.
+/// - `iter`: The Rayon parallel iterator to be consumed. Neither it nor its items need to be
+///   `'static`: they can borrow from the caller, the pipeline is done when `par_bridge` returns.
 /// - `f`: A function that takes a sequential iterator (`RayonIntoIter`) over the parallel
-/// iterator's items, enabling sequential processing or collection of the results.
+///   iterator's items, enabling sequential processing or collection of the results.
+///
+/// # Panics
+///
+/// If the parallel pipeline panics, the consumer sees the stream end early. Once `f` returns,
+/// the panic is resumed on the calling thread so that a truncated run is never mistaken for a
+/// successful one. A panic message is resumed as a `String` starting with
+/// `par_bridge producer panicked (bound=N, thread=NAME)`, followed by the original message,
+/// so that a panic hook or a `catch_unwind` up the stack can tell where it came from. `N` is
+/// `unbounded` for the bridges without a bound, and `NAME` is the one given to
+/// [`ParBridge::thread_name`], if any. Other payloads, like the ones of
+/// [`std::panic::panic_any`], are resumed untouched.
+///
+/// If `f` panics, the channel is dropped first, which stops the parallel pipeline, and the
+/// panic is resumed once the thread driving it has exited.
+///
+/// The sequential iterator is lent to `f` and must not outlive it: the thread driving the
+/// parallel pipeline could wait forever for room in the channel. Returning it, or storing it
+/// in the result, panics once `f` returns. When the items borrow from the caller, like the
+/// ones of `par_iter`, the borrow checker rejects it already:
+///
+/// ```compile_fail
+/// use rayon_par_bridge::par_bridge;
+/// use rayon::prelude::*;
+///
+/// let seq_iter = {
+///     let data = (0u32..100).collect::<Vec<_>>();
+///     par_bridge(5, data.par_iter(), |seq_iter| seq_iter)
+/// };
+/// assert_eq!(seq_iter.count(), 100);
+/// ```
+///
+/// Use [`par_bridge_into_iter`] to get an iterator that can be returned.
+///
+/// # Consumer state
+///
+/// `f` is called on the current thread, and only once: it does not need to be `Send` and can
+/// capture a `!Send` state, like an `Rc`-based cache or the handle of a C library. Only the
+/// parallel iterator and its items cross threads and must be `Send`, a pipeline of `Rc`s is
+/// rejected:
+///
+/// ```compile_fail
+/// use std::rc::Rc;
+///
+/// use rayon_par_bridge::par_bridge;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).map(Rc::new).collect::<Vec<_>>();
+/// par_bridge(5, data.into_iter().par_bridge(), Iterator::count);
+/// ```
+///
+/// The sequential iterator is `Send` when the items are, use [`par_bridge_local`] to make sure
+/// it is not moved to another thread by mistake.
+///
+/// # Tracing
+///
+/// With the `tracing` feature, the thread driving the parallel pipeline runs in a
+/// `par_bridge.producer` span, a child of the span current when `par_bridge` is called, and
+/// `f` runs in a `par_bridge.consumer` span. Both record the `bound`, the producer one also
+/// records the number of items, `len`, when the parallel iterator knows it. Every item sent
+/// through the channel emits a `trace` event in the producer span.
+///
+/// # Where the items are dropped
+///
+/// The items still buffered in the channel when the consumer drops the iterator are dropped
+/// on the consumer thread, at that moment. The items the Rayon workers were about to send, or
+/// computed while the pipeline was stopping, are dropped on the workers. Use
+/// [`par_bridge_drain`] to have every item dropped on the consumer thread.
+///
+/// # Calling from a Rayon worker
+///
+/// The parallel pipeline is driven from a dedicated thread that is not part of any Rayon
+/// pool, it runs on the global pool and never on the thread consuming it. Calling `par_bridge`
+/// from a worker, like inside `pool.install(|| par_bridge(...))`, is fine even if the pool
+/// has a single thread.
+///
+/// However, the consumer keeps its thread busy while waiting for the next item. When every
+/// worker of the global pool is consuming a bridge, like when calling `par_bridge` from the
+/// closures of a parallel iterator running on it, no worker is left to produce the items and
+/// the bridges deadlock. Use [`par_bridge_in`] with a dedicated pool in that situation.
+///
+/// Starting bridges from the consumer closure, recursively, is fine even on a small pool: while
+/// a nested bridge is consumed, the workers waiting for room in the channel of an enclosing one
+/// run the tasks of the nested pipeline instead of blocking. A few more items than `bound` can
+/// then be in flight. This does not apply to a `bound` of zero, whose handoff always blocks.
 pub fn par_bridge<I, F, R>(bound: usize, iter: I, f: F) -> R
 where
     I: IntoParallelIterator + Send,
     F: FnOnce(RayonIntoIter<I::Item>) -> R,
 {
-    std::thread::scope(|s| {
-        let (send, recv) = mpsc::sync_channel(bound);
-        s.spawn(move || iter.into_par_iter().try_for_each(|x| send.send(x).ok()));
-        f(RayonIntoIter(recv.into_iter()))
+    bridge(
+        bound,
+        |send| {
+            send_all(iter.into_par_iter(), &send);
+        },
+        f,
+    )
+}
+
+/// Same as [`par_bridge`] but the bound cannot be zero.
+///
+/// A zero bound makes a rendezvous channel where every Rayon worker waits for the consumer to
+/// take its item, which is rarely intended. Taking a [`NonZeroUsize`] rules it out when the
+/// bound comes from a configuration for example.
+///
+/// # Examples
+///
+/// ```
+/// use std::num::NonZeroUsize;
+///
+/// use rayon_par_bridge::par_bridge_nz;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let bound = NonZeroUsize::new(5).unwrap();
+///
+/// let result: Vec<_> = par_bridge_nz(bound, data.into_par_iter(), |seq_iter| seq_iter.collect());
+/// assert_eq!(result.len(), 100);
+/// ```
+pub fn par_bridge_nz<I, F, R>(bound: NonZeroUsize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    par_bridge(bound.get(), iter, f)
+}
+
+/// Same as [`par_bridge`] but takes a parallel iterator directly.
+///
+/// Every parallel iterator can be passed to [`par_bridge`], but naming the type of a chain of
+/// adapters through [`IntoParallelIterator`] can be a hassle in generic code. This function is
+/// bounded on [`ParallelIterator`] instead.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_iter;
+/// use rayon::prelude::*;
+///
+/// let parallel_pipeline = rayon::iter::repeat_n(2u32, 100).map(|num| num * 2);
+///
+/// let sum = par_bridge_iter(5, parallel_pipeline, |seq_iter| seq_iter.sum::<u32>());
+/// assert_eq!(sum, 400);
+/// ```
+pub fn par_bridge_iter<P, F, R>(bound: usize, par_iter: P, f: F) -> R
+where
+    P: ParallelIterator,
+    F: FnOnce(RayonIntoIter<P::Item>) -> R,
+{
+    bridge(bound, |send| send_all(par_iter, &send), f)
+}
+
+/// Same as [`par_bridge`] but merges the items of several parallel iterators into a single
+/// sequential stream.
+///
+/// The pipelines run concurrently on the Rayon pool and share the channel, their items are
+/// interleaved in completion order. The consumer sees the end of the stream once all of them
+/// are done.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_merge;
+/// use rayon::prelude::*;
+///
+/// let shards = vec![vec![1u32, 2, 3], vec![4, 5], vec![6]];
+/// let parallel_pipelines = shards.into_iter().map(|shard| shard.into_par_iter());
+///
+/// let sum = par_bridge_merge(5, parallel_pipelines, |seq_iter| seq_iter.sum::<u32>());
+/// assert_eq!(sum, 21);
+/// ```
+pub fn par_bridge_merge<I, F, R>(bound: usize, iters: impl IntoIterator<Item = I>, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let iters: Vec<_> = iters.into_iter().collect();
+    par_bridge(bound, iters.into_par_iter().flatten(), f)
+}
+
+/// Same as [`par_bridge`] but spawns the thread driving the parallel pipeline from `builder`.
+///
+/// This lets you name the producer thread so that it shows up in crash dumps and profilers,
+/// or give it a larger stack. An error is returned if the thread could not be spawned.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use rayon_par_bridge::par_bridge_with_builder;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let builder = thread::Builder::new().name("par-bridge-producer".into()).stack_size(8 << 20);
+/// let result: Vec<_> =
+///     par_bridge_with_builder(builder, 5, parallel_pipeline, |seq_iter| seq_iter.collect())?;
+///
+/// assert_eq!(result.len(), 100);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn par_bridge_with_builder<I, F, R>(
+    builder: thread::Builder,
+    bound: usize,
+    iter: I,
+    f: F,
+) -> io::Result<R>
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge_with(
+        builder,
+        channel::bounded(bound),
+        |send| {
+            send_all(iter.into_par_iter(), &send);
+        },
+        |recv| f(RayonIntoIter::new(recv)),
+    )
+}
+
+/// Same as [`par_bridge`] but calls `setup` on the thread driving the parallel pipeline and on
+/// every worker of the Rayon pool before the pipeline starts.
+///
+/// This is the way to propagate thread-local state, like a logging context, that the parallel
+/// closures read and that is set on the calling thread only. The state is left on the Rayon
+/// workers afterwards, where other parallel iterators will see it too.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+///
+/// use rayon_par_bridge::par_bridge_with_setup;
+/// use rayon::prelude::*;
+///
+/// thread_local! {
+///     static REQUEST_ID: Cell<u64> = const { Cell::new(0) };
+/// }
+///
+/// let request_id = 42;
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| (REQUEST_ID.get(), num));
+///
+/// let setup = || REQUEST_ID.set(request_id);
+/// let ids: Vec<_> = par_bridge_with_setup(5, parallel_pipeline, setup, |seq_iter| {
+///     seq_iter.map(|(id, _)| id).collect()
+/// });
+/// assert!(ids.iter().all(|&id| id == 42));
+/// ```
+pub fn par_bridge_with_setup<I, S, F, R>(bound: usize, iter: I, setup: S, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    S: Fn() + Sync + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge(
+        bound,
+        |send| {
+            setup();
+            rayon::broadcast(|_| setup());
+            send_all(iter.into_par_iter(), &send);
+        },
+        f,
+    )
+}
+
+/// Same as [`par_bridge`] but calls `finalize` on the thread driving the parallel pipeline,
+/// exactly once, right after the pipeline is done.
+///
+/// This is the place for teardown that must follow every parallel closure, like flushing a
+/// buffer the workers shared or closing a file they wrote to. The ordering is as follows:
+///
+/// - `finalize` runs after the last item was sent, when the parallel pipeline completed, was
+///   stopped by the consumer dropping the iterator, or panicked. In the latter case, the panic
+///   is resumed once `finalize` returns.
+/// - The consumer may still be pulling the items buffered in the channel while `finalize` runs,
+///   but it only sees the end of the stream once `finalize` returned.
+/// - `finalize` returned by the time `par_bridge_with_finalizer` returns.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use rayon_par_bridge::par_bridge_with_finalizer;
+/// use rayon::prelude::*;
+///
+/// let flushed = AtomicBool::new(false);
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let finalize = || flushed.store(true, Ordering::Relaxed);
+///
+/// let count = par_bridge_with_finalizer(5, data.into_par_iter(), finalize, |seq_iter| {
+///     let count = seq_iter.count();
+///     // The end of the stream was seen.
+///     assert!(flushed.load(Ordering::Relaxed));
+///     count
+/// });
+/// assert_eq!(count, 100);
+/// ```
+pub fn par_bridge_with_finalizer<I, Fin, F, R>(bound: usize, iter: I, finalize: Fin, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    Fin: FnOnce() + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge(
+        bound,
+        |send| {
+            let sent = panic::catch_unwind(AssertUnwindSafe(|| {
+                send_all(iter.into_par_iter(), &send);
+            }));
+            // The channel is only closed once `send` is dropped, after `finalize` returned.
+            finalize();
+            if let Err(payload) = sent {
+                panic::resume_unwind(payload);
+            }
+        },
+        f,
+    )
+}
+
+/// Same as [`par_bridge`] but runs the parallel pipeline in `pool` rather than in the
+/// global Rayon pool.
+///
+/// Only the parallel work is confined to the pool, `f` keeps running on the current thread.
+/// Note that when called from one of the workers of `pool`, the consumer keeps that worker
+/// busy for the whole run.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_in;
+/// use rayon::prelude::*;
+///
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let result: Vec<_> = par_bridge_in(&pool, 5, parallel_pipeline, |seq_iter| seq_iter.collect());
+/// assert_eq!(result.len(), 100);
+/// ```
+pub fn par_bridge_in<I, F, R>(pool: &ThreadPool, bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge(
+        bound,
+        |send| {
+            pool.install(|| send_all(iter.into_par_iter(), &send));
+        },
+        f,
+    )
+}
+
+/// The number of channel slots per Rayon worker chosen by [`par_bridge_auto`].
+const AUTO_BOUND_FACTOR: usize = 4;
+
+/// Same as [`par_bridge`] but chooses the bound from the number of threads of the current
+/// Rayon pool, see [`auto_bound`].
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_auto;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let result: Vec<_> = par_bridge_auto(data.into_par_iter(), |seq_iter| seq_iter.collect());
+///
+/// assert_eq!(result.len(), 100);
+/// ```
+pub fn par_bridge_auto<I, F, R>(iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    par_bridge(auto_bound(), iter, f)
+}
+
+/// Returns the bound [`par_bridge_auto`] uses when called from the current thread.
+///
+/// Each Rayon worker of the current pool gets four slots in the channel: one for the item it
+/// is sending and some slack so that it can compute its next items while the consumer is busy.
+/// With a single thread, this still leaves room for a few items. Outside of a Rayon worker,
+/// the current pool is the global one, which is also the one driving the parallel pipeline.
+pub fn auto_bound() -> usize {
+    rayon::current_num_threads() * AUTO_BOUND_FACTOR
+}
+
+/// Same as [`par_bridge`] but drives the parallel pipeline from a job of the current Rayon pool
+/// instead of a dedicated thread.
+///
+/// Called from a worker of a pool, like inside `pool.install(...)`, the parallel pipeline runs
+/// on that pool with its configuration and thread-local state, where [`par_bridge`] would run
+/// it on the global pool. Called from outside of any pool, it runs on the global pool.
+///
+/// # Panics
+///
+/// Panics if called from a worker of a pool with a single thread: the consumer keeps its
+/// thread busy, no thread would be left to drive the parallel pipeline.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_in_place;
+/// use rayon::prelude::*;
+///
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+/// let data = (0u32..100).collect::<Vec<_>>();
+///
+/// let count = pool.install(|| par_bridge_in_place(5, data.into_par_iter(), Iterator::count));
+/// assert_eq!(count, 100);
+/// ```
+pub fn par_bridge_in_place<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    assert!(
+        rayon::current_thread_index().is_none() || rayon::current_num_threads() > 1,
+        "par_bridge_in_place needs a second worker to drive the parallel pipeline"
+    );
+
+    let (send, recv) = channel::bounded(bound);
+    // The scope waits for the producer, which stops once the receiver is dropped with `f`.
+    rayon::in_place_scope(|s| {
+        s.spawn(move |_| send_all(iter.into_par_iter(), &send));
+        let lent = Lent::default();
+        let result = f(RayonIntoIter::new(IntoIter::new(recv, &lent)));
+        ensure_returned(&lent, result)
     })
 }
 
-/// An `Iterator` over the elements returned by a parallel rayon pipeline.
-pub struct RayonIntoIter<T>(IntoIter<T>);
+/// Same as [`par_bridge`] but without any bound on the number of items waiting to be consumed.
+///
+/// The Rayon workers never block when sending an item and can immediately go pick up more
+/// work, which helps when the items are small and the consumer is the actual bottleneck.
+///
+/// # Memory usage
+///
+/// Nothing slows down the parallel pipeline when the consumer falls behind: every item
+/// produced but not yet consumed stays in memory, which can grow up to the whole output
+/// of the pipeline.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_unbounded;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let result: Vec<_> = par_bridge_unbounded(parallel_pipeline, |seq_iter| seq_iter.collect());
+/// assert_eq!(result.len(), 100);
+/// ```
+pub fn par_bridge_unbounded<I, F, R>(iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge_channel(
+        channel::unbounded(),
+        |send| {
+            send_all(iter.into_par_iter(), &send);
+        },
+        f,
+    )
+}
 
-impl<T> Iterator for RayonIntoIter<T> {
-    type Item = T;
+/// Same as [`par_bridge`] but calls `on_before_send` on the Rayon worker about to send each
+/// item through the channel.
+///
+/// This is meant for tests that need to control the interleaving of the producer and the
+/// consumer: the hook can sleep to make the producer slower than the consumer, or wait on a
+/// barrier or a channel to release the items one at a time. Returning from the hook lets the
+/// item be sent.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use rayon_par_bridge::par_bridge_with_hooks;
+/// use rayon::prelude::*;
+///
+/// let about_to_send = AtomicUsize::new(0);
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let count = par_bridge_with_hooks(
+///     5,
+///     data.into_par_iter(),
+///     |_| {
+///         about_to_send.fetch_add(1, Ordering::Relaxed);
+///     },
+///     Iterator::count,
+/// );
+///
+/// assert_eq!(count, 100);
+/// assert_eq!(about_to_send.into_inner(), 100);
+/// ```
+pub fn par_bridge_with_hooks<I, H, F, R>(bound: usize, iter: I, on_before_send: H, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    H: Fn(&I::Item) + Sync + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge(bound, |send| send_all(iter.into_par_iter().inspect(on_before_send), &send), f)
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next()
+/// An extension trait to bridge a parallel pipeline right where it is written.
+pub trait ParBridgeExt: IntoParallelIterator + Send + Sized {
+    /// Consumes the parallel pipeline sequentially, see [`par_bridge`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_par_bridge::ParBridgeExt;
+    /// use rayon::prelude::*;
+    ///
+    /// let data = (0u32..100).collect::<Vec<_>>();
+    ///
+    /// // Use `seq_bridge` to consume the parallel pipeline results sequentially
+    /// let mut result: Vec<_> =
+    ///     data.into_par_iter().map(|num| num * 2).seq_bridge(5, |seq_iter| seq_iter.collect());
+    ///
+    /// assert_eq!(result.len(), 100);
+    ///
+    /// // Numbers can be out-of-order
+    /// result.sort_unstable();
+    /// assert_eq!(result[0], 0);
+    /// assert_eq!(result[1], 2);
+    /// ```
+    fn seq_bridge<F, R>(self, bound: usize, f: F) -> R
+    where
+        F: FnOnce(RayonIntoIter<Self::Item>) -> R,
+    {
+        par_bridge(bound, self, f)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use rayon::prelude::*;
-
-    use super::*;
+impl<I: IntoParallelIterator + Send> ParBridgeExt for I {}
 
-    #[test]
-    fn single_thread() {
-        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
-        pool.install(|| {
-            let data = (0u32..100).collect::<Vec<_>>();
-            let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
-            let mut result: Vec<_> =
-                par_bridge(5, parallel_pipeline, |seq_iter| seq_iter.collect());
+/// Transforms a Rayon parallel iterator of `Result`s into a sequentially processed iterator
+/// of the successful values, stopping at the first error.
+///
+/// As soon as any item is an `Err`, the producer stops feeding the channel and the remaining
+/// parallel work is cancelled. The consumer `f` still receives the `Ok` values that made it
+/// through before the error, and once it returns, the error is reported instead of its result.
+/// When several items fail, only the first one to complete is reported.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::try_par_bridge;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num.checked_sub(1).ok_or(num));
+///
+/// let result = try_par_bridge(5, parallel_pipeline, |seq_iter| seq_iter.count());
+/// assert_eq!(result, Err(0));
+/// ```
+pub fn try_par_bridge<I, F, R, T, E>(bound: usize, iter: I, f: F) -> Result<R, E>
+where
+    I: IntoParallelIterator<Item = Result<T, E>> + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+    T: Send,
+    E: Send,
+{
+    let error = Mutex::new(None);
+    let result = bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| match x {
+                Ok(x) => send.send(x).ok(),
+                Err(e) => {
+                    error.lock().unwrap().get_or_insert(e);
+                    None
+                }
+            });
+        },
+        f,
+    );
 
-            assert_eq!(result.len(), 100);
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
 
-            result.sort_unstable();
+/// Transforms a Rayon parallel iterator of `Result`s into a sequentially processed iterator
+/// of the successful values, collecting the errors on the side.
+///
+/// Unlike [`try_par_bridge`], an error does not stop the parallel pipeline. The errors are
+/// gathered by the Rayon workers, in completion order, and returned along with the result of
+/// `f`. If `f` stops early, the errors of the items that were not computed are missing.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_partition;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num.checked_sub(10).ok_or(num));
+///
+/// let (count, errors) = par_bridge_partition(5, parallel_pipeline, |seq_iter| seq_iter.count());
+/// assert_eq!(count, 90);
+/// assert_eq!(errors.len(), 10);
+/// ```
+pub fn par_bridge_partition<I, T, E, F, R>(bound: usize, iter: I, f: F) -> (R, Vec<E>)
+where
+    I: IntoParallelIterator<Item = Result<T, E>> + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+    T: Send,
+    E: Send,
+{
+    let errors = Mutex::new(Vec::new());
+    let result = bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| match x {
+                Ok(x) => send.send(x).ok(),
+                Err(e) => {
+                    errors.lock().unwrap().push(e);
+                    Some(())
+                }
+            });
+        },
+        f,
+    );
+
+    (result, errors.into_inner().unwrap())
+}
+
+/// Same as [`try_par_bridge`] but an error does not stop the parallel pipeline: `f` consumes
+/// every successful value and the first error is returned once it is done.
+///
+/// This suits consumers applying side effects, like writing the values to a file, that must
+/// be applied to every successful value before the error is reported. The first error is the
+/// first one computed by the Rayon workers, the later ones are dropped.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_drain_then_err;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num.checked_sub(10).ok_or(num));
+///
+/// let mut written = 0;
+/// let result = par_bridge_drain_then_err(5, parallel_pipeline, |seq_iter| {
+///     seq_iter.for_each(|_| written += 1)
+/// });
+///
+/// assert!(result.is_err());
+/// assert_eq!(written, 90);
+/// ```
+pub fn par_bridge_drain_then_err<I, T, E, F, R>(bound: usize, iter: I, f: F) -> Result<R, E>
+where
+    I: IntoParallelIterator<Item = Result<T, E>> + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+    T: Send,
+    E: Send,
+{
+    let error = Mutex::new(None);
+    let result = bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| match x {
+                Ok(x) => send.send(x).ok(),
+                Err(e) => {
+                    error.lock().unwrap().get_or_insert(e);
+                    Some(())
+                }
+            });
+        },
+        f,
+    );
+
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// Same as [`par_bridge`] but for consumers that can fail.
+///
+/// When `f` returns an `Err`, the iterator it was given has been dropped: the following sends
+/// of the producer fail and the parallel pipeline stops without computing the remaining items.
+/// The error is then returned as is. See [`try_par_bridge`] for pipelines producing errors.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_try;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let result = par_bridge_try(5, data.into_par_iter(), |mut seq_iter| {
+///     match seq_iter.find(|num| num % 10 == 7) {
+///         Some(num) => Err(num),
+///         None => Ok(()),
+///     }
+/// });
+///
+/// assert!(result.is_err());
+/// ```
+pub fn par_bridge_try<I, F, R, E>(bound: usize, iter: I, f: F) -> Result<R, E>
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> Result<R, E>,
+{
+    par_bridge(bound, iter, f)
+}
+
+/// Sends the items of the parallel pipeline until the receiver is dropped.
+fn send_all<P: ParallelIterator>(par_iter: P, send: &Sender<P::Item>) {
+    trace::record_len(par_iter.opt_len());
+    // A break only means that the consumer is done, there is nothing more to report.
+    let _ = par_iter.try_for_each(|x| match send.send(x) {
+        Ok(()) => ControlFlow::Continue(()),
+        // Breaking stops Rayon from splitting and computing more items nobody will receive.
+        Err(_) => ControlFlow::Break(()),
+    });
+}
+
+/// Runs `produce` on a scoped thread, feeding the channel consumed by `f` on the current one.
+///
+/// A panic in `produce` is caught and resumed once `f` has returned, see [`bridge_with`].
+fn bridge<T, P, F, R>(bound: usize, produce: P, f: F) -> R
+where
+    T: Send,
+    P: FnOnce(Sender<T>) + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+{
+    bridge_channel(channel::bounded(bound), produce, f)
+}
+
+/// Prefixes the message of a producer panic with the bound and the name of the current thread,
+/// the one that drove the parallel pipeline.
+fn with_context(bound: Option<usize>, payload: Box<dyn Any + Send>) -> Box<dyn Any + Send> {
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(payload) => return payload,
+        },
+    };
+    let bound = bound.map_or_else(|| "unbounded".to_string(), |bound| bound.to_string());
+    let thread = thread::current();
+    let name = thread.name().unwrap_or("<unnamed>");
+    Box::new(format!("par_bridge producer panicked (bound={bound}, thread={name}): {message}"))
+}
+
+/// Same as [`bridge`] but over an already created channel.
+fn bridge_channel<T, P, F, R>(channel: (Sender<T>, Receiver<T>), produce: P, f: F) -> R
+where
+    T: Send,
+    P: FnOnce(Sender<T>) + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+{
+    bridge_with(thread::Builder::new(), channel, produce, |recv| f(RayonIntoIter::new(recv)))
+        .expect("failed to spawn the producer thread")
+}
+
+/// Runs `produce` on a thread spawned from `builder`, feeding the channel
+/// drained by `consume` on the current one.
+///
+/// A panic in `produce` is caught and resumed once `consume` has returned, with the bound of
+/// the channel and the name of the thread added to its message, see [`with_context`].
+fn bridge_with<T, P, C, R>(
+    builder: thread::Builder,
+    channel: (Sender<T>, Receiver<T>),
+    produce: P,
+    consume: C,
+) -> io::Result<R>
+where
+    T: Send,
+    P: FnOnce(Sender<T>) + Send,
+    C: FnOnce(IntoIter<T>) -> R,
+{
+    let (send, recv) = channel;
+    #[cfg(test)]
+    SPAWNED.set(SPAWNED.get() + 1);
+    let (bound, parent) = (recv.bound(), trace::Span::current());
+    thread::scope(|s| {
+        let producer = builder.spawn_scoped(s, move || {
+            let span = trace::producer(&parent, bound);
+            let send = send.in_span(span.clone());
+            span.in_scope(|| panic::catch_unwind(AssertUnwindSafe(|| produce(send))))
+                .map_err(|payload| with_context(bound, payload))
+        })?;
+        // The receiver is dropped with `consume`, even when it unwinds, which unblocks the
+        // producer before we wait for it. The consumer panic wins over the producer one.
+        let _consuming = recv.consuming();
+        let lent = Lent::default();
+        let consume = || consume(IntoIter::new(recv, &lent));
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| trace::consumer(bound).in_scope(consume)))
+                .map(|result| ensure_returned(&lent, result));
+        let produced = producer.join();
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        match produced {
+            Ok(Err(payload)) | Err(payload) => panic::resume_unwind(payload),
+            Ok(Ok(())) => Ok(result),
+        }
+    })
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The number of producer threads spawned from the current thread.
+    static SPAWNED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Panics if the consumer kept the iterator it was lent, in its result for example, as the
+/// producer would wait for it forever. `result` is dropped first to unblock the producer.
+fn ensure_returned<R>(lent: &Lent, result: R) -> R {
+    if !lent.is_returned() {
+        drop(result);
+        panic!("the sequential iterator must not outlive the closure it was given to");
+    }
+    result
+}
+
+/// An `Iterator` over the elements returned by a parallel rayon pipeline.
+///
+/// It is `Send` when the items are, and can be consumed from another thread than the one
+/// that received it.
+pub struct RayonIntoIter<T> {
+    inner: Inner<T>,
+    counters: Option<Arc<Counters>>,
+    /// The item returned by [`RayonIntoIter::peek`], yielded by the next call to `next`.
+    peeked: Option<T>,
+    /// Whether a receive found the parallel pipeline done, see [`RayonIntoIter::is_done`].
+    done: bool,
+    /// Joins the detached producer thread, dropped after the receiver.
+    _guard: Option<BridgeGuard>,
+}
+
+enum Inner<T> {
+    Unordered(IntoIter<T>),
+    Ordered(Reorder<T>),
+    /// The batches received and the remaining items of the current one.
+    Batched(IntoIter<Vec<T>>, vec::IntoIter<T>),
+    /// A receiver shared between several consumers.
+    Shared(SharedReceiver<T>),
+    /// A receiver that a [`BridgeGuard`] can drop while the iterator is alive.
+    Guarded(Arc<Guarded<T>>),
+    Weighted(Weighted<T>),
+    /// The items of `Copy` types, received in arrays.
+    Unpacked(Unpack<T>),
+    Windowed(Windowed<T>),
+    Cooperative(Cooperative<T>),
+    Adaptive(Adaptive<T>),
+    Rendezvous(SlotReceiver<T>),
+    Tunable(Tunable<T>),
+}
+
+impl<T> RayonIntoIter<T> {
+    /// Returns a reference to the next item without consuming it, waiting for the parallel
+    /// pipeline to send one if needed.
+    ///
+    /// The item is kept aside and returned by the next call to `next`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_par_bridge::par_bridge;
+    /// use rayon::prelude::*;
+    ///
+    /// let data = (0u32..100).collect::<Vec<_>>();
+    /// let count = par_bridge(5, data.into_par_iter(), |mut seq_iter| {
+    ///     let first = *seq_iter.peek().unwrap();
+    ///     assert_eq!(seq_iter.next(), Some(first));
+    ///     seq_iter.count() + 1
+    /// });
+    ///
+    /// assert_eq!(count, 100);
+    /// ```
+    pub fn peek(&mut self) -> Option<&T> {
+        if self.peeked.is_none() {
+            self.peeked = self.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    /// Returns the next item if one was already received, without waiting for the parallel
+    /// pipeline.
+    ///
+    /// This lets an event loop drain the items available and come back later. An error tells
+    /// whether the channel is only empty for now or the parallel pipeline is done, after which
+    /// `next` returns `None` too. An ordered iterator is empty until the next item in order is
+    /// received, even if later ones were.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_par_bridge::{par_bridge, TryNextError};
+    /// use rayon::prelude::*;
+    ///
+    /// let data = (0u32..100).collect::<Vec<_>>();
+    /// let count = par_bridge(5, data.into_par_iter(), |mut seq_iter| {
+    ///     let mut count = 0;
+    ///     loop {
+    ///         match seq_iter.try_next() {
+    ///             Ok(_) => count += 1,
+    ///             // Do something else in the meantime.
+    ///             Err(TryNextError::Empty) => std::thread::yield_now(),
+    ///             Err(TryNextError::Disconnected) => break count,
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(count, 100);
+    /// ```
+    pub fn try_next(&mut self) -> Result<T, TryNextError> {
+        self.recv(false).map_err(|e| match e {
+            TryRecvError::Empty => TryNextError::Empty,
+            TryRecvError::Disconnected => TryNextError::Disconnected,
+        })
+    }
+
+    /// Returns up to `max` of the items already received, without waiting for the parallel
+    /// pipeline.
+    ///
+    /// This lets a game loop or a GUI process whatever is ready once per frame. The returned
+    /// `Vec` is empty when no item is ready, which does not mean the parallel pipeline is done:
+    /// use [`RayonIntoIter::is_done`] to tell.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_par_bridge::par_bridge;
+    /// use rayon::prelude::*;
+    ///
+    /// let data = (0u32..100).collect::<Vec<_>>();
+    /// let count = par_bridge(5, data.into_par_iter(), |mut seq_iter| {
+    ///     let mut count = 0;
+    ///     while !seq_iter.is_done() {
+    ///         count += seq_iter.drain_available(16).len();
+    ///         // Render the frame.
+    ///         std::thread::yield_now();
+    ///     }
+    ///     count
+    /// });
+    ///
+    /// assert_eq!(count, 100);
+    /// ```
+    pub fn drain_available(&mut self, max: usize) -> Vec<T> {
+        let mut items = Vec::new();
+        while items.len() < max {
+            match self.recv(false) {
+                Ok(item) => items.push(item),
+                Err(_) => break,
+            }
+        }
+        items
+    }
+
+    /// Whether the thread driving the parallel pipeline is done sending items, even if some of
+    /// them were not yielded yet.
+    ///
+    /// This tells the tail of the stream apart before `next` returns `None`, to flush more
+    /// eagerly for example. It does not receive anything, the remaining items are still
+    /// yielded by the following calls to `next`. An ordered iterator can be finished while
+    /// items are still waiting for the ones before them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_par_bridge::par_bridge;
+    /// use rayon::prelude::*;
+    ///
+    /// let data = (0u32..100).collect::<Vec<_>>();
+    /// let count = par_bridge(200, data.into_par_iter(), |mut seq_iter| {
+    ///     let mut count = 0;
+    ///     while !seq_iter.producer_finished() {
+    ///         count += seq_iter.drain_available(10).len();
+    ///     }
+    ///     // The last items are still there.
+    ///     count + seq_iter.count()
+    /// });
+    ///
+    /// assert_eq!(count, 100);
+    /// ```
+    pub fn producer_finished(&self) -> bool {
+        match &self.inner {
+            Inner::Unordered(inner) => inner.is_disconnected(),
+            Inner::Ordered(inner) => inner.producer_finished(),
+            Inner::Batched(inner, _) => inner.is_disconnected(),
+            Inner::Shared(inner) => inner.is_disconnected(),
+            Inner::Guarded(inner) => inner.producer_finished(),
+            Inner::Weighted(inner) => inner.producer_finished(),
+            Inner::Unpacked(inner) => inner.producer_finished(),
+            Inner::Windowed(inner) => inner.producer_finished(),
+            Inner::Cooperative(inner) => inner.producer_finished(),
+            Inner::Adaptive(inner) => inner.producer_finished(),
+            Inner::Rendezvous(inner) => inner.producer_finished(),
+            Inner::Tunable(inner) => inner.producer_finished(),
+        }
+    }
+
+    /// Whether the parallel pipeline is done and every item was yielded.
+    ///
+    /// It only turns `true` once a call to `next`, [`RayonIntoIter::try_next`] or
+    /// [`RayonIntoIter::drain_available`] found the stream over, it never waits for it.
+    pub fn is_done(&self) -> bool {
+        self.done && self.peeked.is_none()
+    }
+
+    /// Receives the next item, waiting for it if `block` is set.
+    fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        if let Some(item) = self.peeked.take() {
+            return Ok(item);
+        }
+        if let Some(counters) = &self.counters {
+            counters.receiving();
+        }
+        let started = self.counters.as_ref().filter(|_| block).map(|_| Instant::now());
+        let item = match &mut self.inner {
+            Inner::Unordered(inner) => inner.recv(block),
+            Inner::Ordered(inner) => inner.recv(block),
+            Inner::Batched(inner, batch) => loop {
+                match batch.next() {
+                    Some(item) => break Ok(item),
+                    None => match inner.recv(block) {
+                        Ok(next_batch) => *batch = next_batch.into_iter(),
+                        Err(e) => break Err(e),
+                    },
+                }
+            },
+            Inner::Shared(inner) => inner.recv(block),
+            Inner::Guarded(inner) => inner.recv(block),
+            Inner::Weighted(inner) => inner.recv(block),
+            Inner::Unpacked(inner) => inner.recv(block),
+            Inner::Windowed(inner) => inner.recv(block),
+            Inner::Cooperative(inner) => inner.recv(block),
+            Inner::Adaptive(inner) => inner.recv(block),
+            Inner::Rendezvous(inner) => inner.recv(block),
+            Inner::Tunable(inner) => inner.recv(block),
+        };
+        if let Some(counters) = &self.counters {
+            if let Some(started) = started {
+                counters.consumer_waited(started.elapsed());
+            }
+            match item {
+                Ok(_) => counters.delivered(),
+                Err(TryRecvError::Empty) => counters.gave_up(),
+                Err(TryRecvError::Disconnected) => (),
+            }
+        }
+        if let Err(TryRecvError::Disconnected) = item {
+            self.done = true;
+        }
+        item
+    }
+
+    fn new(inner: IntoIter<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Unordered(inner))
+    }
+
+    /// An iterator over a channel without any producer, which yields nothing.
+    fn empty() -> RayonIntoIter<T> {
+        let (_, recv) = channel::bounded(0);
+        RayonIntoIter::new(IntoIter::new(recv, &Lent::default()))
+    }
+
+    fn ordered(inner: Reorder<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Ordered(inner))
+    }
+
+    fn batched(inner: IntoIter<Vec<T>>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Batched(inner, Vec::new().into_iter()))
+    }
+
+    fn shared(inner: SharedReceiver<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Shared(inner))
+    }
+
+    fn guarded(inner: Arc<Guarded<T>>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Guarded(inner))
+    }
+
+    fn weighted(inner: Weighted<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Weighted(inner))
+    }
+
+    fn unpack(inner: Unpack<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Unpacked(inner))
+    }
+
+    fn windowed(inner: Windowed<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Windowed(inner))
+    }
+
+    fn cooperative(inner: Cooperative<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Cooperative(inner))
+    }
+
+    fn adaptive(inner: Adaptive<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Adaptive(inner))
+    }
+
+    fn rendezvous(inner: SlotReceiver<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Rendezvous(inner))
+    }
+
+    fn tunable(inner: Tunable<T>) -> RayonIntoIter<T> {
+        RayonIntoIter::from_inner(Inner::Tunable(inner))
+    }
+
+    fn from_inner(inner: Inner<T>) -> RayonIntoIter<T> {
+        RayonIntoIter { inner, counters: None, peeked: None, done: false, _guard: None }
+    }
+
+    fn with_counters(self, counters: Arc<Counters>) -> RayonIntoIter<T> {
+        RayonIntoIter { counters: Some(counters), ..self }
+    }
+
+    fn with_guard(self, guard: BridgeGuard) -> RayonIntoIter<T> {
+        RayonIntoIter { _guard: Some(guard), ..self }
+    }
+}
+
+impl<T> Iterator for RayonIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv(true).ok()
+    }
+
+    /// Consumes the whole stream and returns the last item received.
+    ///
+    /// The items are received in completion order, which item is the last one is arbitrary
+    /// unless the items are ordered.
+    fn last(mut self) -> Option<Self::Item> {
+        let peeked = self.peeked.take();
+        match (&mut self.inner, &self.counters) {
+            // The channel can be drained directly, without going through `next` for each item.
+            (Inner::Unordered(inner), None) => inner.last().or(peeked),
+            _ => self.fold(peeked, |_, item| Some(item)),
+        }
+    }
+
+    /// Skips `n` items and returns the next one, the items are skipped in completion order.
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        if let Some(item) = self.peeked.take() {
+            match n.checked_sub(1) {
+                None => return Some(item),
+                Some(rest) => n = rest,
+            }
+        }
+        match (&mut self.inner, &self.counters) {
+            (Inner::Unordered(inner), None) => inner.nth(n),
+            _ => {
+                for _ in 0..n {
+                    self.next()?;
+                }
+                self.next()
+            }
+        }
+    }
+}
+
+// The iterator only ends once the channel is disconnected and drained, which is permanent:
+// the senders are dropped when the producer ends and no new sender is ever created, and a
+// receiver taken away by a `BridgeGuard` is never given back.
+impl<T> FusedIterator for RayonIntoIter<T> {}
+
+/// Why [`RayonIntoIter::try_next`] did not return an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryNextError {
+    /// No item is available right now, the parallel pipeline may send more.
+    Empty,
+    /// The parallel pipeline is done and every item was received.
+    Disconnected,
+}
+
+impl fmt::Display for TryNextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryNextError::Empty => f.write_str("no item is available yet"),
+            TryNextError::Disconnected => f.write_str("the parallel pipeline is done"),
+        }
+    }
+}
+
+impl std::error::Error for TryNextError {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn single_thread() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        pool.install(|| {
+            let data = (0u32..100).collect::<Vec<_>>();
+            let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+            let mut result: Vec<_> =
+                par_bridge(5, parallel_pipeline, |seq_iter| seq_iter.collect());
+
+            assert_eq!(result.len(), 100);
+
+            result.sort_unstable();
             assert_eq!(result[0], 0);
             assert_eq!(result[1], 2);
         });
     }
+
+    /// Runs `f` on another thread, panicking if it doesn't complete in time.
+    pub(crate) fn with_timeout<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+        let (send, recv) = mpsc::channel();
+        thread::spawn(move || send.send(f()).unwrap());
+        recv.recv_timeout(std::time::Duration::from_secs(30)).expect("the bridge deadlocked")
+    }
+
+    #[test]
+    fn recursive_bridges_on_a_small_pool() {
+        /// Sums the leaves of a tree of bridges, each consumer bridging the level below.
+        fn leaves(pool: &ThreadPool, depth: u32) -> u64 {
+            par_bridge_in(pool, 1, (0u64..4).into_par_iter(), |seq_iter| {
+                seq_iter.map(|num| if depth == 0 { num } else { leaves(pool, depth - 1) }).sum()
+            })
+        }
+
+        let sum = with_timeout(|| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+            leaves(&pool, 4)
+        });
+
+        assert_eq!(sum, 6 * 4u64.pow(4));
+    }
+
+    #[test]
+    fn from_a_single_thread_pool_worker() {
+        let count = with_timeout(|| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+            pool.install(|| par_bridge(1, (0u32..10_000).into_par_iter(), Iterator::count))
+        });
+
+        assert_eq!(count, 10_000);
+    }
+
+    #[test]
+    fn from_parallel_closures_of_another_pool() {
+        let counts = with_timeout(|| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+            pool.install(|| {
+                (0..8)
+                    .into_par_iter()
+                    .map(|_| par_bridge(1, (0u32..1_000).into_par_iter(), Iterator::count))
+                    .collect::<Vec<_>>()
+            })
+        });
+
+        assert_eq!(counts, [1_000; 8]);
+    }
+
+    #[test]
+    fn returned_iterator_panics_instead_of_hanging() {
+        let panicked = with_timeout(|| {
+            panic::catch_unwind(|| {
+                par_bridge(1, (0u32..1000).into_par_iter(), |mut seq_iter| {
+                    seq_iter.next();
+                    seq_iter
+                })
+            })
+            .is_err()
+        });
+
+        assert!(panicked);
+    }
+
+    #[test]
+    fn consumer_state_need_not_be_send() {
+        let cache = Rc::new(RefCell::new(Vec::new()));
+        let data = (0u32..1000).collect::<Vec<_>>();
+
+        let count = par_bridge(5, data.par_iter().copied(), |seq_iter| {
+            let cache = cache.clone();
+            seq_iter.inspect(|&num| cache.borrow_mut().push(num)).count()
+        });
+
+        assert_eq!(count, 1000);
+        let mut cached = cache.take();
+        cached.sort_unstable();
+        assert_eq!(cached, data);
+    }
+
+    #[test]
+    fn rendezvous_channel() {
+        let mut result = with_timeout(|| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+            par_bridge_in(&pool, 0, (0u32..1000).into_par_iter(), |seq_iter| {
+                seq_iter.collect::<Vec<_>>()
+            })
+        });
+
+        result.sort_unstable();
+        assert_eq!(result, (0u32..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn non_zero_bound_of_one() {
+        let bound = NonZeroUsize::MIN;
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let mut with_nz: Vec<_> = par_bridge_nz(bound, data.par_iter().copied(), Iterator::collect);
+        let mut with_one: Vec<_> = par_bridge(1, data.par_iter().copied(), Iterator::collect);
+
+        with_nz.sort_unstable();
+        with_one.sort_unstable();
+        assert_eq!(with_nz, with_one);
+        assert_eq!(with_nz, data);
+    }
+
+    #[test]
+    fn peek_then_drain() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let (first, mut rest) = par_bridge(5, data.par_iter().copied(), |mut seq_iter| {
+            let first = *seq_iter.peek().unwrap();
+            assert_eq!(seq_iter.peek(), Some(&first));
+            (first, seq_iter.collect::<Vec<_>>())
+        });
+
+        assert_eq!(rest[0], first);
+        rest.sort_unstable();
+        assert_eq!(rest, data);
+    }
+
+    #[test]
+    fn try_next_outcomes() {
+        let (sent, first_sent) = mpsc::channel();
+        let (finish, finished) = mpsc::channel::<()>();
+        let result = par_bridge_with(
+            4,
+            move |send| {
+                send.send(1u32).map_err(drop)?;
+                sent.send(()).map_err(drop)?;
+                finished.recv().map_err(drop)
+            },
+            |mut seq_iter| {
+                first_sent.recv().unwrap();
+                assert_eq!(seq_iter.try_next(), Ok(1));
+                assert_eq!(seq_iter.try_next(), Err(TryNextError::Empty));
+
+                finish.send(()).unwrap();
+                let outcome = loop {
+                    match seq_iter.try_next() {
+                        Err(TryNextError::Empty) => thread::yield_now(),
+                        outcome => break outcome,
+                    }
+                };
+                assert_eq!(outcome, Err(TryNextError::Disconnected));
+                assert_eq!(seq_iter.next(), None);
+            },
+        );
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn producer_finished_before_the_last_items() {
+        let (resume, resumed) = mpsc::channel();
+        let result = par_bridge_with(
+            100,
+            move |send| {
+                for num in 0u32..10 {
+                    send.send(num).unwrap();
+                }
+                resumed.recv().unwrap();
+                for num in 10u32..20 {
+                    send.send(num).unwrap();
+                }
+                Ok::<_, ()>(())
+            },
+            |mut seq_iter| {
+                assert_eq!(seq_iter.next(), Some(0));
+                assert!(!seq_iter.producer_finished());
+                resume.send(()).unwrap();
+
+                let start = Instant::now();
+                while !seq_iter.producer_finished() {
+                    assert!(start.elapsed().as_secs() < 30, "the producer never finished");
+                    thread::yield_now();
+                }
+                // Every item but the first one is still buffered.
+                assert!(!seq_iter.is_done());
+                let rest: Vec<_> = seq_iter.by_ref().collect();
+                assert!(seq_iter.producer_finished());
+                rest
+            },
+        );
+
+        assert_eq!(result, Ok((1..20).collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn drain_available_across_frames() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let (mut drained, frames) = par_bridge(64, data.par_iter().copied(), |mut seq_iter| {
+            let (mut drained, mut frames) = (Vec::new(), 0);
+            while !seq_iter.is_done() {
+                let items = seq_iter.drain_available(100);
+                assert!(items.len() <= 100);
+                drained.extend(items);
+                frames += 1;
+            }
+            assert_eq!(seq_iter.drain_available(100), []);
+            assert_eq!(seq_iter.next(), None);
+            (drained, frames)
+        });
+
+        assert!(frames >= 100);
+        drained.sort_unstable();
+        assert_eq!(drained, data);
+    }
+
+    #[test]
+    fn last_and_nth() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let last = par_bridge(5, data.par_iter().copied(), Iterator::last);
+        assert!(last.is_some_and(|num| num < 1000));
+
+        let (fifth, rest) = par_bridge(5, data.par_iter().copied(), |mut seq_iter| {
+            (seq_iter.nth(5), seq_iter.count())
+        });
+        assert!(fifth.is_some());
+        assert_eq!(rest, 1000 - 6);
+
+        // The peeked item counts as the first one to skip.
+        let (fifth, rest) = par_bridge(5, data.par_iter().copied(), |mut seq_iter| {
+            seq_iter.peek();
+            (seq_iter.nth(5), seq_iter.count())
+        });
+        assert!(fifth.is_some());
+        assert_eq!(rest, 1000 - 6);
+    }
+
+    #[test]
+    fn fused_past_exhaustion() {
+        let data = (0u32..10).collect::<Vec<_>>();
+        par_bridge(5, data.into_par_iter(), |mut seq_iter| {
+            assert_eq!(seq_iter.by_ref().count(), 10);
+            for _ in 0..5 {
+                assert_eq!(seq_iter.next(), None);
+            }
+        });
+    }
+
+    #[test]
+    fn consumer_panic_stops_the_producer() {
+        let message = with_timeout(|| {
+            let payload = panic::catch_unwind(|| {
+                par_bridge(1, (0u32..10_000).into_par_iter(), |seq_iter| {
+                    seq_iter.take(3).for_each(drop);
+                    panic!("consumer failed");
+                })
+            })
+            .unwrap_err();
+            *payload.downcast::<&str>().unwrap()
+        });
+
+        assert_eq!(message, "consumer failed");
+    }
+
+    #[test]
+    #[should_panic(expected = "item 42 failed")]
+    fn producer_panic_is_propagated() {
+        let data = (0u32..100).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(|num| {
+            if num == 42 {
+                panic!("item {num} failed");
+            }
+            num
+        });
+        let _result: Vec<_> = par_bridge(5, parallel_pipeline, |seq_iter| seq_iter.collect());
+    }
+
+    #[test]
+    fn producer_panic_message_has_context() {
+        let payload = panic::catch_unwind(|| {
+            let parallel_pipeline = (0u32..100).into_par_iter().map(|num| {
+                if num == 42 {
+                    panic!("item {num} failed");
+                }
+                num
+            });
+            par_bridge(5, parallel_pipeline, Iterator::count)
+        })
+        .unwrap_err();
+
+        let message = payload.downcast::<String>().unwrap();
+        assert!(message.starts_with("par_bridge producer panicked (bound=5, thread="), "{message}");
+        assert!(message.ends_with(": item 42 failed"), "{message}");
+
+        let payload = panic::catch_unwind(|| {
+            let parallel_pipeline = (0u32..100).into_par_iter().inspect(|&num| {
+                if num == 42 {
+                    panic!("item {num} failed");
+                }
+            });
+            par_bridge_unbounded(parallel_pipeline, Iterator::count)
+        })
+        .unwrap_err();
+        let message = payload.downcast::<String>().unwrap();
+        assert!(
+            message.starts_with("par_bridge producer panicked (bound=unbounded, "),
+            "{message}"
+        );
+
+        let payload = panic::catch_unwind(|| {
+            par_bridge(
+                5,
+                (0u32..100).into_par_iter().map(|_| panic::panic_any(42u8)),
+                Iterator::count,
+            )
+        })
+        .unwrap_err();
+        assert_eq!(payload.downcast::<u8>().ok().map(|code| *code), Some(42));
+    }
+
+    #[test]
+    fn early_drop_stops_the_producer() {
+        // A single worker sends the items in turn, it can only get one item ahead of the
+        // consumer before blocking on the channel holding another one.
+        let about_to_send = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().with_min_len(usize::MAX);
+
+        let count = par_bridge_with_hooks(
+            1,
+            parallel_pipeline,
+            |_| {
+                about_to_send.fetch_add(1, Ordering::Relaxed);
+            },
+            |seq_iter| seq_iter.take(5).count(),
+        );
+
+        assert_eq!(count, 5);
+        assert!(about_to_send.into_inner() <= 7);
+    }
+
+    #[test]
+    fn consumer_error_stops_the_producer() {
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let result: Result<(), _> =
+            par_bridge_try(5, parallel_pipeline, |seq_iter| match seq_iter.take(3).count() {
+                3 => Err("seen enough"),
+                _ => Ok(()),
+            });
+
+        assert_eq!(result, Err("seen enough"));
+        assert!(processed.load(Ordering::Relaxed) < 10_000);
+    }
+
+    #[test]
+    fn named_producer_thread() {
+        /// Records the name of the thread converting it into a parallel iterator,
+        /// the parallel closures themselves run on the Rayon workers.
+        struct Named(Vec<u32>, Arc<Mutex<Option<String>>>);
+
+        impl IntoParallelIterator for Named {
+            type Iter = rayon::vec::IntoIter<u32>;
+            type Item = u32;
+
+            fn into_par_iter(self) -> Self::Iter {
+                *self.1.lock().unwrap() = thread::current().name().map(String::from);
+                self.0.into_par_iter()
+            }
+        }
+
+        let name = Arc::new(Mutex::new(None));
+        let data = (0u32..100).collect::<Vec<_>>();
+        let builder = thread::Builder::new().name("par-bridge-producer".into());
+        let count = par_bridge_with_builder(builder, 5, Named(data, name.clone()), Iterator::count);
+
+        assert_eq!(count.unwrap(), 100);
+        assert_eq!(name.lock().unwrap().as_deref(), Some("par-bridge-producer"));
+    }
+
+    #[test]
+    fn finalizer_runs_once() {
+        let calls = AtomicUsize::new(0);
+        let finalize = || {
+            calls.fetch_add(1, Ordering::SeqCst);
+        };
+        let count =
+            par_bridge_with_finalizer(1, (0u32..100_000).into_par_iter(), finalize, |seq_iter| {
+                seq_iter.take(3).count()
+            });
+        assert_eq!(count, 3);
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 1);
+
+        let finalize = || {
+            calls.fetch_add(1, Ordering::SeqCst);
+        };
+        let calls_at_the_end =
+            par_bridge_with_finalizer(1, (0u32..1000).into_par_iter(), finalize, |seq_iter| {
+                seq_iter.for_each(drop);
+                calls.load(Ordering::SeqCst)
+            });
+        assert_eq!(calls_at_the_end, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn setup_reaches_the_parallel_closures() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static CONTEXT: Cell<u32> = const { Cell::new(0) };
+        }
+
+        /// Records the context of the thread converting it into a parallel iterator,
+        /// and yields the context of the threads computing its items.
+        struct Driven(Vec<u32>, Arc<Mutex<u32>>);
+
+        impl IntoParallelIterator for Driven {
+            type Iter = rayon::iter::Map<rayon::vec::IntoIter<u32>, fn(u32) -> u32>;
+            type Item = u32;
+
+            fn into_par_iter(self) -> Self::Iter {
+                *self.1.lock().unwrap() = CONTEXT.get();
+                self.0.into_par_iter().map(|_| CONTEXT.get())
+            }
+        }
+
+        let producer_context = Arc::new(Mutex::new(0));
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let (contexts, consumer_context) = par_bridge_with_setup(
+            5,
+            Driven(data, producer_context.clone()),
+            || CONTEXT.set(7),
+            |seq_iter| (seq_iter.collect::<Vec<_>>(), CONTEXT.get()),
+        );
+
+        assert_eq!(*producer_context.lock().unwrap(), 7);
+        assert!(contexts.iter().all(|&context| context == 7));
+        assert_eq!(consumer_context, 0);
+    }
+
+    #[test]
+    fn explicit_pool() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let data = (0u32..100).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(|_| rayon::current_num_threads());
+        let result: Vec<_> =
+            par_bridge_in(&pool, 5, parallel_pipeline, |seq_iter| seq_iter.collect());
+
+        assert_eq!(result.len(), 100);
+        assert!(result.iter().all(|&num_threads| num_threads == 2));
+    }
+
+    #[test]
+    fn borrowed_items() {
+        let large_vec = (0..1000).map(|num| num.to_string()).collect::<Vec<_>>();
+        let mut slices: Vec<&str> =
+            par_bridge(5, large_vec.par_iter().map(String::as_str), |seq_iter| seq_iter.collect());
+
+        slices.sort_unstable_by_key(|s| s.parse::<u32>().unwrap());
+        assert!(slices.iter().zip(&large_vec).all(|(s, owned)| std::ptr::eq(*s, owned.as_str())));
+    }
+
+    #[test]
+    fn bridge_a_parallel_iterator() {
+        fn evens(n: usize) -> impl ParallelIterator<Item = u32> {
+            rayon::iter::repeat_n(2u32, usize::MAX).take_any(n)
+        }
+
+        let result: Vec<_> = par_bridge_iter(5, evens(1000), |seq_iter| seq_iter.collect());
+        assert_eq!(result, vec![2; 1000]);
+    }
+
+    #[test]
+    fn auto_bound_follows_the_pool() {
+        let bound_in = |num_threads| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build().unwrap();
+            pool.install(auto_bound)
+        };
+
+        assert_eq!(bound_in(1), 4);
+        assert_eq!(bound_in(8), 32);
+    }
+
+    #[test]
+    fn merge_three_pipelines() {
+        let mut result: Vec<_> = par_bridge_merge(
+            5,
+            (0u32..3).map(|shard| (shard * 1000..(shard + 1) * 1000).into_par_iter()),
+            |seq_iter| seq_iter.collect(),
+        );
+
+        assert_eq!(result.len(), 3000);
+        result.sort_unstable();
+        assert_eq!(result, (0u32..3000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn in_place_runs_on_the_current_pool() {
+        thread_local!(static POOL_ID: std::cell::Cell<u32> = const { std::cell::Cell::new(0) });
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .start_handler(|_| POOL_ID.with(|id| id.set(7)))
+            .build()
+            .unwrap();
+        let pipeline = || (0u32..1000).into_par_iter().map(|_| POOL_ID.with(|id| id.get()));
+
+        let in_place: Vec<_> =
+            pool.install(|| par_bridge_in_place(5, pipeline(), |seq_iter| seq_iter.collect()));
+        assert_eq!(in_place, vec![7; 1000]);
+
+        let scoped: Vec<_> =
+            pool.install(|| par_bridge(5, pipeline(), |seq_iter| seq_iter.collect()));
+        assert!(scoped.iter().all(|&id| id == 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "needs a second worker")]
+    fn in_place_on_a_single_thread_pool() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        pool.install(|| par_bridge_in_place(5, (0u32..100).into_par_iter(), Iterator::count));
+    }
+
+    #[test]
+    fn unbounded_matches_bounded() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        pool.install(|| {
+            let data = (0u32..100).collect::<Vec<_>>();
+            let mut bounded: Vec<_> =
+                par_bridge(5, data.par_iter().map(|num| num * 2), |seq_iter| seq_iter.collect());
+            let mut unbounded: Vec<_> =
+                par_bridge_unbounded(data.par_iter().map(|num| num * 2), |seq_iter| {
+                    seq_iter.collect()
+                });
+
+            bounded.sort_unstable();
+            unbounded.sort_unstable();
+            assert_eq!(bounded, unbounded);
+        });
+    }
+
+    #[test]
+    fn drain_then_err_applies_every_ok() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline =
+            data.par_iter().map(|&num| if num % 1000 == 999 { Err(num) } else { Ok(num) });
+
+        let mut applied = Vec::new();
+        let result =
+            par_bridge_drain_then_err(5, parallel_pipeline, |seq_iter| applied.extend(seq_iter));
+
+        assert!(result.is_err_and(|num| num % 1000 == 999));
+        applied.sort_unstable();
+        assert_eq!(applied, data.into_iter().filter(|num| num % 1000 != 999).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_par_bridge_first_error() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let parallel_pipeline =
+            data.into_par_iter().map(|num| if num == 37 { Err(num) } else { Ok(num) });
+        let result = try_par_bridge(5, parallel_pipeline, |seq_iter| seq_iter.collect::<Vec<_>>());
+
+        assert_eq!(result, Err(37));
+    }
+
+    #[test]
+    fn partition_oks_and_errors() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let parallel_pipeline =
+            data.into_par_iter().map(|num| if num % 10 == 3 { Err(num) } else { Ok(num) });
+
+        let (oks, mut errors) =
+            par_bridge_partition(5, parallel_pipeline, |seq_iter| seq_iter.collect::<Vec<_>>());
+
+        assert_eq!(oks.len(), 900);
+        assert!(oks.iter().all(|num| num % 10 != 3));
+        errors.sort_unstable();
+        assert_eq!(errors, (0..100).map(|num| num * 10 + 3).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_par_bridge_no_error() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(Ok::<_, ()>);
+        let result = try_par_bridge(5, parallel_pipeline, |seq_iter| seq_iter.count());
+
+        assert_eq!(result, Ok(1000));
+    }
 }
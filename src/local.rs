@@ -0,0 +1,63 @@
+use std::marker::PhantomData;
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::{par_bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but the sequential iterator given to `f` cannot
+/// leave the current thread.
+///
+/// The items are still received on the current thread only, as with
+/// [`par_bridge`](crate::par_bridge), but the iterator is not `Send`, so that it cannot be
+/// moved to a Rayon task or a scoped thread by mistake. This suits consumers tied to the
+/// current thread, like the ones driving a GUI toolkit or a `!Send` resource captured by `f`:
+///
+/// ```compile_fail
+/// use rayon_par_bridge::par_bridge_local;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// par_bridge_local(5, data.into_par_iter(), |seq_iter| {
+///     std::thread::scope(|s| s.spawn(|| seq_iter.count()).join().unwrap())
+/// });
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// use rayon_par_bridge::par_bridge_local;
+/// use rayon::prelude::*;
+///
+/// let seen = Rc::new(RefCell::new(Vec::new()));
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// par_bridge_local(5, data.into_par_iter(), |seq_iter| {
+///     seq_iter.for_each(|num| seen.borrow_mut().push(num))
+/// });
+///
+/// assert_eq!(seen.borrow().len(), 100);
+/// ```
+pub fn par_bridge_local<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(LocalRayonIter<I::Item>) -> R,
+{
+    par_bridge(bound, iter, |inner| f(LocalRayonIter { inner, _not_send: PhantomData }))
+}
+
+/// An `Iterator` over the elements returned by a parallel rayon pipeline that is confined to
+/// the thread consuming it, see [`par_bridge_local`].
+pub struct LocalRayonIter<T> {
+    inner: RayonIntoIter<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> Iterator for LocalRayonIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
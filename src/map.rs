@@ -0,0 +1,61 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{bridge, send_all, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but transforms the items with `map` on the
+/// Rayon workers before sending them.
+///
+/// Only the mapped values go through the channel, which saves memory when the consumer only
+/// needs a small projection of large items, and lets `f` be written against the projected
+/// type whatever the pipeline it is bridged to.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_map;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).map(|num| vec![num; 1000]).collect::<Vec<_>>();
+///
+/// let sum = par_bridge_map(5, data.into_par_iter(), |big| big[0], |seq_iter| {
+///     seq_iter.sum::<u32>()
+/// });
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_map<I, M, U, F, R>(bound: usize, iter: I, map: M, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    M: Fn(I::Item) -> U + Sync + Send,
+    U: Send,
+    F: FnOnce(RayonIntoIter<U>) -> R,
+{
+    bridge(bound, |send| send_all(iter.into_par_iter().map(map), &send), f)
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn map_runs_on_the_workers() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let mut result: Vec<_> = par_bridge_map(
+            5,
+            data.into_par_iter(),
+            |num| (num, rayon::current_thread_index().is_some()),
+            |seq_iter| {
+                assert!(rayon::current_thread_index().is_none());
+                seq_iter.collect()
+            },
+        );
+
+        result.sort_unstable();
+        assert!(result.iter().all(|&(_, on_a_worker)| on_a_worker));
+        assert_eq!(
+            result.into_iter().map(|(num, _)| num).collect::<Vec<_>>(),
+            (0..1000).collect::<Vec<_>>()
+        );
+    }
+}
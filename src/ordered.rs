@@ -0,0 +1,466 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::{io, thread};
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, IntoIter, TryRecvError};
+use crate::{bridge_with, RayonIntoIter};
+
+/// Transforms a Rayon indexed parallel iterator into a sequentially processed iterator that
+/// yields the items in their original order.
+///
+/// Items still complete in any order on the Rayon side, the consumer reorders them using a
+/// small buffer keyed by the item index. The `bound` parameter is both the size of the channel
+/// and the maximum distance an item can run ahead of the next item to be yielded: a producer
+/// that gets too far ahead waits for the consumer to catch up, which keeps the reordering
+/// buffer from growing when an early item is slow. A `bound` of zero behaves like a `bound` of
+/// one, so that the next item to be yielded can always be sent.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_ordered;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let result: Vec<_> = par_bridge_ordered(5, parallel_pipeline, |seq_iter| seq_iter.collect());
+///
+/// assert_eq!(result, (0u32..100).map(|num| num * 2).collect::<Vec<_>>());
+/// ```
+pub fn par_bridge_ordered<I, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    F: FnOnce(OrderedRayonIter<I::Item>) -> R,
+{
+    bridge_ordered(thread::Builder::new(), bound, iter, |reorder| f(OrderedRayonIter(reorder)))
+        .expect("failed to spawn the producer thread")
+}
+
+/// Same as [`par_bridge_ordered`] but skips the items whose key equals the key of the previous
+/// item yielded, like [`Vec::dedup_by_key`].
+///
+/// Only consecutive duplicates in the original order are removed, which makes it suited to
+/// deduplicating sorted keys. The keys are computed on the current thread.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_ordered_dedup_by_key;
+/// use rayon::prelude::*;
+///
+/// let data = vec![1u32, 1, 2, 3, 3, 3, 1];
+/// let result: Vec<_> =
+///     par_bridge_ordered_dedup_by_key(5, |&num| num, data.into_par_iter(), Iterator::collect);
+///
+/// assert_eq!(result, [1, 2, 3, 1]);
+/// ```
+pub fn par_bridge_ordered_dedup_by_key<I, K, Key, F, R>(bound: usize, key: Key, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    K: PartialEq,
+    Key: FnMut(&I::Item) -> K,
+    F: FnOnce(DedupRayonIter<I::Item, K, Key>) -> R,
+{
+    par_bridge_ordered(bound, iter, |inner| f(DedupRayonIter { inner, key, last: None }))
+}
+
+/// An `Iterator` over the elements returned by an indexed parallel rayon pipeline, in their
+/// original order and without consecutive duplicates, see [`par_bridge_ordered_dedup_by_key`].
+pub struct DedupRayonIter<T, K, Key> {
+    inner: OrderedRayonIter<T>,
+    key: Key,
+    /// The key of the last item yielded.
+    last: Option<K>,
+}
+
+impl<T, K: PartialEq, Key: FnMut(&T) -> K> Iterator for DedupRayonIter<T, K, Key> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        for item in self.inner.by_ref() {
+            let key = (self.key)(&item);
+            if self.last.as_ref() != Some(&key) {
+                self.last = Some(key);
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+/// Same as [`par_bridge_ordered`] but groups the consecutive items sharing the same key, like
+/// a `GROUP BY` over a sorted stream.
+///
+/// `f` receives each maximal run of items with equal keys, in the original order, along with
+/// their key. A group is yielded once an item with a different key is received or the parallel
+/// pipeline is done, so only the current group is buffered. The keys are computed on the
+/// current thread.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_ordered_group_by;
+/// use rayon::prelude::*;
+///
+/// let data = vec![1u32, 1, 2, 3, 3];
+/// let groups: Vec<_> =
+///     par_bridge_ordered_group_by(5, |&num| num, data.into_par_iter(), Iterator::collect);
+///
+/// assert_eq!(groups, [(1, vec![1, 1]), (2, vec![2]), (3, vec![3, 3])]);
+/// ```
+pub fn par_bridge_ordered_group_by<I, K, Key, F, R>(bound: usize, key: Key, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    K: PartialEq,
+    Key: FnMut(&I::Item) -> K,
+    F: FnOnce(RayonGroups<K, I::Item, Key>) -> R,
+{
+    par_bridge_ordered(bound, iter, |inner| f(RayonGroups { inner, key, next: None }))
+}
+
+/// An `Iterator` over the runs of items sharing the same key returned by an indexed parallel
+/// rayon pipeline, see [`par_bridge_ordered_group_by`].
+pub struct RayonGroups<K, T, Key> {
+    inner: OrderedRayonIter<T>,
+    key: Key,
+    /// The first item of the next group, already received.
+    next: Option<(K, T)>,
+}
+
+impl<K: PartialEq, T, Key: FnMut(&T) -> K> Iterator for RayonGroups<K, T, Key> {
+    type Item = (K, Vec<T>);
+
+    fn next(&mut self) -> Option<(K, Vec<T>)> {
+        let (group_key, first) = match self.next.take() {
+            Some(next) => next,
+            None => {
+                let item = self.inner.next()?;
+                ((self.key)(&item), item)
+            }
+        };
+
+        let mut group = vec![first];
+        for item in self.inner.by_ref() {
+            let key = (self.key)(&item);
+            if key != group_key {
+                self.next = Some((key, item));
+                break;
+            }
+            group.push(item);
+        }
+        Some((group_key, group))
+    }
+}
+
+/// Runs the ordered producer on a thread spawned from `builder`.
+pub(crate) fn bridge_ordered<I, C, R>(
+    builder: thread::Builder,
+    bound: usize,
+    iter: I,
+    consume: C,
+) -> io::Result<R>
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    C: FnOnce(Reorder<I::Item>) -> R,
+{
+    bridge_indexed(builder, bound, bound, iter, |recv, window| {
+        consume(Reorder { recv, pending: VecDeque::new(), window })
+    })
+}
+
+/// Runs a producer sending the items along with their index, none of them going
+/// `window_size` indices past the next index the consumer is waiting for.
+fn bridge_indexed<I, C, R>(
+    builder: thread::Builder,
+    bound: usize,
+    window_size: usize,
+    iter: I,
+    consume: C,
+) -> io::Result<R>
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    C: FnOnce(IntoIter<(usize, I::Item)>, Arc<Window>) -> R,
+{
+    let window = Arc::new(Window::new(window_size));
+    let producer_window = window.clone();
+    bridge_with(
+        builder,
+        channel::bounded(bound),
+        move |send| {
+            iter.into_par_iter().enumerate().try_for_each(|(i, x)| {
+                producer_window.wait_for(i);
+                send.send((i, x)).ok()
+            });
+        },
+        |recv| consume(recv, window),
+    )
+}
+
+/// Transforms a Rayon indexed parallel iterator into a sequentially processed iterator that
+/// yields the items approximately in their original order.
+///
+/// An item is yielded fewer than `window` positions away from its original position: the
+/// item at index `i` is the `p`-th one to be yielded with `|p - i| < window`. A `window` of one
+/// gives the same order as [`par_bridge_ordered`], larger windows let the items be yielded as
+/// soon as they are received instead of waiting for the earlier ones.
+///
+/// This is enforced on the producer side, an item waits before being sent until every item
+/// `window` or more indices before it has been received. The consumer never buffers items,
+/// it only remembers which of the next `window` indices it received. The `bound` parameter is
+/// the size of the channel.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_windowed;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let result: Vec<_> = par_bridge_windowed(5, 4, data.into_par_iter(), |seq_iter| seq_iter.collect());
+///
+/// for (position, &num) in result.iter().enumerate() {
+///     assert!(position.abs_diff(num as usize) < 4);
+/// }
+/// ```
+pub fn par_bridge_windowed<I, F, R>(bound: usize, window: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    bridge_indexed(thread::Builder::new(), bound, window, iter, |recv, window| {
+        f(RayonIntoIter::windowed(Windowed { recv, received: VecDeque::new(), window }))
+    })
+    .expect("failed to spawn the producer thread")
+}
+
+/// An `Iterator` over the elements returned by an indexed parallel rayon pipeline,
+/// in their original order.
+pub struct OrderedRayonIter<T>(Reorder<T>);
+
+impl<T> Iterator for OrderedRayonIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.recv(true).ok()
+    }
+}
+
+/// Yields the items received along with their index in index order.
+pub(crate) struct Reorder<T> {
+    recv: IntoIter<(usize, T)>,
+    /// The out-of-order items, the front slot is the next index to be yielded.
+    pending: VecDeque<Option<T>>,
+    window: Arc<Window>,
+}
+
+impl<T> Reorder<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        loop {
+            if let Some(x) = self.pending.front_mut().and_then(Option::take) {
+                self.pending.pop_front();
+                self.window.advance();
+                return Ok(x);
+            }
+
+            // If the producer stops before sending the next index there is
+            // a gap we can never fill, the stream ends there.
+            let (i, x) = self.recv.recv(block)?;
+            let offset = i - self.window.next.load(Ordering::Acquire);
+            if self.pending.len() <= offset {
+                self.pending.resize_with(offset + 1, || None);
+            }
+            self.pending[offset] = Some(x);
+        }
+    }
+}
+
+impl<T> Drop for Reorder<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        self.window.close();
+    }
+}
+
+/// Yields the items as they are received, keeping the window at the first index not received.
+pub(crate) struct Windowed<T> {
+    recv: IntoIter<(usize, T)>,
+    /// Whether the indices after the first one not received were received.
+    received: VecDeque<bool>,
+    window: Arc<Window>,
+}
+
+impl<T> Windowed<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        let (i, x) = self.recv.recv(block)?;
+        let offset = i - self.window.next.load(Ordering::Acquire);
+        if self.received.len() <= offset {
+            self.received.resize(offset + 1, false);
+        }
+        self.received[offset] = true;
+
+        while self.received.front() == Some(&true) {
+            self.received.pop_front();
+            self.window.advance();
+        }
+        Ok(x)
+    }
+}
+
+impl<T> Drop for Windowed<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        self.window.close();
+    }
+}
+
+/// Limits how far ahead of the consumer the producers can send items.
+struct Window {
+    /// The index of the next item to be yielded, `usize::MAX` once closed.
+    next: AtomicUsize,
+    size: usize,
+    lock: Mutex<()>,
+    cond: Condvar,
+}
+
+impl Window {
+    fn new(size: usize) -> Window {
+        Window {
+            next: AtomicUsize::new(0),
+            size: size.max(1),
+            lock: Mutex::new(()),
+            cond: Condvar::new(),
+        }
+    }
+
+    fn is_open_for(&self, index: usize) -> bool {
+        index < self.next.load(Ordering::Acquire).saturating_add(self.size)
+    }
+
+    /// Blocks until the item at `index` fits in the window.
+    fn wait_for(&self, index: usize) {
+        if self.is_open_for(index) {
+            return;
+        }
+
+        let mut guard = self.lock.lock().unwrap();
+        while !self.is_open_for(index) {
+            guard = self.cond.wait(guard).unwrap();
+        }
+    }
+
+    fn advance(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.next.fetch_add(1, Ordering::Release);
+        self.cond.notify_all();
+    }
+
+    fn close(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.next.store(usize::MAX, Ordering::Release);
+        self.cond.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn shuffled_completion_is_reordered() {
+        let data = (0u64..200).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(|num| {
+            thread::sleep(Duration::from_micros((num % 7) * 200));
+            num
+        });
+        let result: Vec<_> =
+            par_bridge_ordered(4, parallel_pipeline, |seq_iter| seq_iter.collect());
+
+        assert_eq!(result, (0u64..200).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn early_drop_does_not_block_producers() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let result: Vec<_> =
+            par_bridge_ordered(1, data.into_par_iter(), |seq_iter| seq_iter.take(3).collect());
+
+        assert_eq!(result, [0, 1, 2]);
+    }
+
+    #[test]
+    fn windowed_displacement_is_bounded() {
+        let data = (0u64..2000).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(|num| {
+            thread::sleep(Duration::from_micros((num % 13) * 50));
+            num
+        });
+        let result: Vec<_> =
+            par_bridge_windowed(4, 8, parallel_pipeline, |seq_iter| seq_iter.collect());
+
+        assert_eq!(result.len(), 2000);
+        for (position, &num) in result.iter().enumerate() {
+            assert!(position.abs_diff(num as usize) < 8, "{num} yielded at {position}");
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_the_first_of_each_run() {
+        let data = (0u64..3000).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(|num| {
+            thread::sleep(Duration::from_micros((num % 7) * 20));
+            (num / 10, num)
+        });
+        let result: Vec<_> = par_bridge_ordered_dedup_by_key(
+            4,
+            |&(key, _)| key,
+            parallel_pipeline,
+            |seq_iter| seq_iter.map(|(_, num)| num).collect(),
+        );
+
+        assert_eq!(result, (0u64..300).map(|key| key * 10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn groups_follow_the_original_order() {
+        let data = vec!['a', 'a', 'b', 'b', 'b', 'c'];
+        let parallel_pipeline = data.into_par_iter().enumerate().map(|(index, key)| {
+            thread::sleep(Duration::from_micros((5 - index as u64) * 500));
+            (key, index)
+        });
+        let groups: Vec<_> = par_bridge_ordered_group_by(
+            2,
+            |&(key, _)| key,
+            parallel_pipeline,
+            |seq_iter| {
+                seq_iter
+                    .map(|(key, items)| (key, items.into_iter().map(|(_, index)| index).collect()))
+                    .collect()
+            },
+        );
+
+        assert_eq!(groups, [('a', vec![0, 1]), ('b', vec![2, 3, 4]), ('c', vec![5])]);
+    }
+}
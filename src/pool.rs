@@ -0,0 +1,152 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::channel::{self, IntoIter, Lent};
+use crate::{ensure_returned, send_all, with_context, RayonIntoIter};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A long-lived thread to drive the parallel pipelines of many bridges.
+///
+/// [`par_bridge`](crate::par_bridge) spawns a new thread on every call, which shows up when
+/// bridging many small pipelines in a loop. A `BridgePool` spawns its thread once and reuses
+/// it for every call to [`BridgePool::bridge`]. As this thread outlives the calls, the parallel
+/// iterators must be `'static`. Dropping the pool waits for its thread to exit.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::BridgePool;
+/// use rayon::prelude::*;
+///
+/// let mut pool = BridgePool::new();
+/// for i in 0u32..10 {
+///     let sum = pool.bridge(5, (0..i).into_par_iter(), |seq_iter| seq_iter.sum::<u32>());
+///     assert_eq!(sum, (0..i).sum());
+/// }
+/// ```
+pub struct BridgePool {
+    jobs: Option<mpsc::Sender<Job>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl BridgePool {
+    /// Spawns the thread of the pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread could not be spawned.
+    pub fn new() -> BridgePool {
+        let (jobs, recv) = mpsc::channel::<Job>();
+        let worker = thread::Builder::new()
+            .name("par-bridge-pool".to_string())
+            .spawn(move || recv.into_iter().for_each(|job| job()))
+            .expect("failed to spawn the producer thread");
+        BridgePool { jobs: Some(jobs), worker: Some(worker) }
+    }
+
+    /// Same as [`par_bridge`](crate::par_bridge) but drives the parallel pipeline from the
+    /// thread of the pool.
+    ///
+    /// The pool drives a single pipeline at a time, which is why it must be borrowed mutably:
+    /// a consumer cannot start another bridge on the same pool while its own pipeline is
+    /// waiting for it.
+    pub fn bridge<I, F, R>(&mut self, bound: usize, iter: I, f: F) -> R
+    where
+        I: IntoParallelIterator + Send + 'static,
+        F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    {
+        let (send, recv) = channel::bounded(bound);
+        let (done, finished) = mpsc::channel::<Result<(), Box<dyn Any + Send>>>();
+        let job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(move || {
+                send_all(iter.into_par_iter(), &send);
+            }))
+            .map_err(|payload| with_context(Some(bound), payload));
+            // The consumer may have returned already.
+            let _ = done.send(result);
+        });
+        self.jobs.as_ref().unwrap().send(job).expect("the pool thread exited");
+
+        // Same as `bridge_with`, the receiver is dropped before we wait for the producer.
+        let _consuming = recv.consuming();
+        let lent = Lent::default();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            f(RayonIntoIter::new(IntoIter::new(recv, &lent)))
+        }))
+        .map(|result| ensure_returned(&lent, result));
+        let produced = finished.recv().expect("the pool thread exited");
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        produced.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        result
+    }
+}
+
+impl Default for BridgePool {
+    fn default() -> BridgePool {
+        BridgePool::new()
+    }
+}
+
+impl Drop for BridgePool {
+    fn drop(&mut self) {
+        // Closing the job channel ends the loop of the thread.
+        drop(self.jobs.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::par_bridge;
+    use crate::tests::with_timeout;
+
+    #[test]
+    fn reused_across_calls() {
+        let mut pool = BridgePool::new();
+        for len in 0u64..200 {
+            let data = (0..len).collect::<Vec<_>>();
+            let mut result: Vec<_> =
+                pool.bridge(3, data.clone().into_par_iter(), Iterator::collect);
+            result.sort_unstable();
+            assert_eq!(result, data);
+        }
+    }
+
+    #[test]
+    fn survives_a_producer_panic() {
+        let mut pool = BridgePool::new();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let parallel_pipeline = (0u32..100).into_par_iter().inspect(|&num| {
+                if num == 42 {
+                    panic!("item {num} failed");
+                }
+            });
+            pool.bridge(5, parallel_pipeline, Iterator::count)
+        }));
+        assert!(result.is_err());
+
+        let count = pool.bridge(5, (0u32..100).into_par_iter(), Iterator::count);
+        assert_eq!(count, 100);
+    }
+
+    #[test]
+    fn nested_bridge_in_the_consumer() {
+        let sum: u64 = with_timeout(|| {
+            let mut pool = BridgePool::new();
+            pool.bridge(1, (0u64..8).into_par_iter(), |seq_iter| {
+                seq_iter.map(|_| par_bridge(1, (0..8).into_par_iter(), Iterator::sum::<u64>)).sum()
+            })
+        });
+        assert_eq!(sum, 8 * 28);
+    }
+}
@@ -0,0 +1,111 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::ThreadPool;
+
+use crate::{bridge, RayonIntoIter};
+
+/// Same as [`par_bridge_in`](crate::par_bridge_in) but gives a `priority` hint for sharing
+/// the workers of `pool` with other bridges, the higher the more urgent.
+///
+/// Rayon has no priorities, this is a cooperative approximation: the workers of the pipeline
+/// call [`rayon::yield_now`] every `priority + 1` items, which lets them run other tasks
+/// queued on the pool in the meantime. A pipeline of priority `0` yields after every item,
+/// one of priority `255` rarely does.
+///
+/// This is best-effort and the effect is often small. A yielding worker runs the tasks of its
+/// own deque first, which usually are other parts of the same pipeline, then steals from the
+/// other workers, and only takes a pipeline just started on the pool, like the one of another
+/// bridge, when there is nothing else to do. The hint helps most when the other pipelines are
+/// already split over the workers.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_priority;
+/// use rayon::prelude::*;
+///
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+/// let data = (0u32..100).collect::<Vec<_>>();
+///
+/// let sum = par_bridge_priority(&pool, 0, 5, data.into_par_iter(), |seq_iter| {
+///     seq_iter.sum::<u32>()
+/// });
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_priority<I, F, R>(
+    pool: &ThreadPool,
+    priority: u8,
+    bound: usize,
+    iter: I,
+    f: F,
+) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let every = usize::from(priority) + 1;
+    let sent = AtomicUsize::new(0);
+    bridge(
+        bound,
+        |send| {
+            pool.install(|| {
+                iter.into_par_iter().try_for_each(|x| {
+                    send.send(x).ok()?;
+                    if sent.fetch_add(1, Ordering::Relaxed) % every == every - 1 {
+                        yield_once();
+                    }
+                    Some(())
+                })
+            });
+        },
+        f,
+    )
+}
+
+thread_local! {
+    /// Whether the current worker is running another task from a yield.
+    static YIELDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs another Rayon task, unless the current thread is already running one from a yield.
+///
+/// The task run is often another part of the same pipeline, that would yield and run another
+/// one in turn until the stack overflows.
+fn yield_once() {
+    if !YIELDING.replace(true) {
+        rayon::yield_now();
+        YIELDING.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn bridges_of_different_priorities_share_a_pool() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let data = (0u64..100_000).collect::<Vec<_>>();
+        let run = |priority| {
+            par_bridge_priority(&pool, priority, 4, data.par_iter(), |seq_iter| {
+                seq_iter.sum::<u64>()
+            })
+        };
+
+        // A priority of zero yields after every item, without nesting the yields.
+        let (urgent, background) = thread::scope(|s| {
+            let background = s.spawn(|| run(0));
+            let urgent = s.spawn(|| run(u8::MAX));
+            (urgent.join().unwrap(), background.join().unwrap())
+        });
+
+        assert_eq!(urgent, 4_999_950_000);
+        assert_eq!(background, 4_999_950_000);
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::mpsc::SendError;
+use std::thread;
+
+use crate::channel::{self, Sender};
+use crate::{bridge_with, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but the items are sent by `produce` instead of
+/// a parallel iterator, and the producer can fail as a whole.
+///
+/// `produce` runs on a dedicated thread and is free to send the items the way it wants,
+/// from Rayon workers or not. When it returns an error, the consumer sees the end of the
+/// stream and, once `f` returns, the error is reported instead of its result.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_with;
+/// use rayon::prelude::*;
+///
+/// let result = par_bridge_with(
+///     5,
+///     |send| {
+///         (0u32..100).into_par_iter().try_for_each(|num| send.send(num).map_err(|_| "stopped"))?;
+///         Err("connection dropped")
+///     },
+///     |seq_iter| seq_iter.count(),
+/// );
+///
+/// assert_eq!(result, Err("connection dropped"));
+/// ```
+pub fn par_bridge_with<T, P, F, R, E>(bound: usize, produce: P, f: F) -> Result<R, E>
+where
+    T: Send,
+    P: FnOnce(BridgeSender<T>) -> Result<(), E> + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+    E: Send,
+{
+    let mut error = None;
+    let result = bridge_with(
+        thread::Builder::new(),
+        channel::bounded(bound),
+        |send| {
+            if let Err(e) = produce(BridgeSender(send)) {
+                error = Some(e);
+            }
+        },
+        |recv| f(RayonIntoIter::new(recv)),
+    )
+    .expect("failed to spawn the producer thread");
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// The sending half of the channel given to the producer of [`par_bridge_with`].
+///
+/// It can be cloned and shared between threads, the consumer sees the end of the stream once
+/// every clone has been dropped.
+pub struct BridgeSender<T>(Sender<T>);
+
+impl<T> BridgeSender<T> {
+    /// Sends an item, waiting while the channel is full.
+    ///
+    /// Fails when the consumer has dropped its iterator, the item is given back in the error.
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        self.0.send(item)
+    }
+}
+
+impl<T> Clone for BridgeSender<T> {
+    fn clone(&self) -> BridgeSender<T> {
+        BridgeSender(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn producer_error_after_some_items() {
+        let mut seen = 0;
+        let result = par_bridge_with(
+            5,
+            |send| {
+                for num in 0u32..50 {
+                    send.send(num).unwrap();
+                }
+                Err("connection dropped")
+            },
+            |seq_iter| seen = seq_iter.count(),
+        );
+
+        assert_eq!(seen, 50);
+        assert_eq!(result, Err("connection dropped"));
+    }
+}
@@ -0,0 +1,87 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::{par_bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but yields at most `max_per_sec` items per second
+/// to `f`, whatever the speed of the parallel pipeline.
+///
+/// The consumer thread sleeps before yielding an item that would come too early, the items are
+/// spaced evenly and no burst is allowed. While it sleeps, the channel fills up and the Rayon
+/// workers block on it, which throttles the parallel pipeline too.
+///
+/// # Panics
+///
+/// Panics if `max_per_sec` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_rate_limited;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..10).collect::<Vec<_>>();
+/// let sum = par_bridge_rate_limited(5, 1000, data.into_par_iter(), |seq_iter| {
+///     seq_iter.sum::<u32>()
+/// });
+///
+/// assert_eq!(sum, 45);
+/// ```
+pub fn par_bridge_rate_limited<I, F, R>(bound: usize, max_per_sec: u32, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RateLimitedRayonIter<I::Item>) -> R,
+{
+    assert!(max_per_sec != 0, "the rate must not be zero");
+    let interval = Duration::from_secs(1) / max_per_sec;
+    par_bridge(bound, iter, |inner| f(RateLimitedRayonIter { inner, interval, next_at: None }))
+}
+
+/// An `Iterator` over the elements returned by a parallel rayon pipeline, spaced in time,
+/// see [`par_bridge_rate_limited`].
+pub struct RateLimitedRayonIter<T> {
+    inner: RayonIntoIter<T>,
+    interval: Duration,
+    /// When the next item can be yielded, `None` before the first one.
+    next_at: Option<Instant>,
+}
+
+impl<T> Iterator for RateLimitedRayonIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let x = self.inner.next()?;
+        let mut now = Instant::now();
+        if let Some(next_at) = self.next_at.filter(|&next_at| next_at > now) {
+            thread::sleep(next_at - now);
+            now = next_at;
+        }
+        // A slow consumer does not earn the right to a burst later.
+        self.next_at = Some(now + self.interval);
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn throughput_is_capped() {
+        let data = (0u32..100).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let mut result =
+            par_bridge_rate_limited(5, 50, data.par_iter().copied(), Iterator::collect::<Vec<_>>);
+        let elapsed = start.elapsed();
+
+        // 99 intervals of 20ms separate the 100 items.
+        assert!(elapsed >= Duration::from_millis(1980), "took {elapsed:?}");
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+}
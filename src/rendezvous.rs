@@ -0,0 +1,185 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{Lent, TryRecvError};
+use crate::{ensure_returned, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) with a `bound` of one, over a single slot rather
+/// than a channel.
+///
+/// The items are handed over through one `Option` guarded by a mutex: a Rayon worker waits
+/// for the slot to be empty before filling it, and the consumer takes the item out. The
+/// behavior is the same as a `bound` of one, at most one item waits for the consumer, but the
+/// synchronization is a lock and a condition variable instead of the general channel.
+///
+/// This is not faster in general: in the `rendezvous` benchmark of this crate, many workers
+/// contending for the slot make it slower than the channel, which spins before parking. This is
+/// why [`par_bridge`](crate::par_bridge) keeps using the channel for a `bound` of one. Measure
+/// both on the target workload before picking this one.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_rendezvous;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let sum = par_bridge_rendezvous(data.into_par_iter(), |seq_iter| seq_iter.sum::<u32>());
+///
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_rendezvous<I, F, R>(iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let slot = Arc::new(Slot::new());
+    let lent = Lent::default();
+    thread::scope(|s| {
+        let producer_slot = slot.clone();
+        let producer = s.spawn(move || {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                // Closes the slot once the pipeline is done, even if it panics.
+                let sender = SlotSender(producer_slot);
+                iter.into_par_iter().try_for_each(|x| sender.0.send(x).ok());
+            }))
+        });
+
+        let recv = SlotReceiver { slot, _lent: lent.clone() };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(RayonIntoIter::rendezvous(recv))))
+            .map(|result| ensure_returned(&lent, result));
+        let produced = producer.join();
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        match produced {
+            Ok(Err(payload)) | Err(payload) => panic::resume_unwind(payload),
+            Ok(Ok(())) => result,
+        }
+    })
+}
+
+/// The item handed over from the Rayon workers to the consumer.
+struct Slot<T> {
+    state: Mutex<SlotState<T>>,
+    /// Notified when the slot is filled or the producer is done.
+    filled: Condvar,
+    /// Notified when the slot is emptied or the consumer is dropped.
+    emptied: Condvar,
+}
+
+struct SlotState<T> {
+    item: Option<T>,
+    producing: bool,
+    consuming: bool,
+}
+
+impl<T> Slot<T> {
+    fn new() -> Slot<T> {
+        Slot {
+            state: Mutex::new(SlotState { item: None, producing: true, consuming: true }),
+            filled: Condvar::new(),
+            emptied: Condvar::new(),
+        }
+    }
+
+    /// Waits for the slot to be empty and fills it, gives the item back if the consumer is gone.
+    fn send(&self, x: T) -> Result<(), T> {
+        let mut state = self.state.lock().unwrap();
+        while state.item.is_some() && state.consuming {
+            state = self.emptied.wait(state).unwrap();
+        }
+        if !state.consuming {
+            return Err(x);
+        }
+        state.item = Some(x);
+        self.filled.notify_one();
+        Ok(())
+    }
+}
+
+/// Marks the end of the items when dropped.
+struct SlotSender<T>(Arc<Slot<T>>);
+
+impl<T> Drop for SlotSender<T> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().producing = false;
+        self.0.filled.notify_one();
+    }
+}
+
+/// Takes the items out of the slot.
+pub(crate) struct SlotReceiver<T> {
+    slot: Arc<Slot<T>>,
+    _lent: Lent,
+}
+
+impl<T> SlotReceiver<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        !self.slot.state.lock().unwrap().producing
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            if let Some(x) = state.item.take() {
+                // Several workers can wait for the slot, only one of them can fill it.
+                self.slot.emptied.notify_one();
+                return Ok(x);
+            }
+            if !state.producing {
+                return Err(TryRecvError::Disconnected);
+            }
+            if !block {
+                return Err(TryRecvError::Empty);
+            }
+            state = self.slot.filled.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T> Drop for SlotReceiver<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        let item = {
+            let mut state = self.slot.state.lock().unwrap();
+            state.consuming = false;
+            state.item.take()
+        };
+        self.slot.emptied.notify_all();
+        // The item left in the slot is dropped here, on the consumer thread.
+        drop(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn every_item_is_delivered_once() {
+        let data = (0u32..100_000).collect::<Vec<_>>();
+        let mut result: Vec<_> = par_bridge_rendezvous(data.par_iter().copied(), Iterator::collect);
+
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn early_drop_stops_the_producer() {
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let count = par_bridge_rendezvous(parallel_pipeline, |seq_iter| seq_iter.take(5).count());
+
+        assert_eq!(count, 5);
+        assert!(processed.load(Ordering::Relaxed) < 10_000);
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but the items are computed by calling `op` with
+/// each index in `0..len`, and the indices that failed are tried again, up to `max_retries`
+/// times.
+///
+/// The first pass calls `op` on every index in parallel and sends the successes to `f` right
+/// away. Each following pass only calls it on the indices that failed during the previous one,
+/// until none is left or `max_retries` passes were run. This suits transient failures, like a
+/// flaky network fetch. The errors of the indices that never succeeded are returned along with
+/// the result of `f`, sorted by index, with the error of their last attempt.
+///
+/// When the consumer stops early, no more pass is started and only the errors of the pass that
+/// was running are returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use rayon_par_bridge::par_bridge_retry;
+///
+/// let first_try = (0..100).map(|_| AtomicBool::new(true)).collect::<Vec<_>>();
+/// let fetch = |index: usize| match first_try[index].swap(false, Ordering::Relaxed) {
+///     true if index % 10 == 0 => Err("timed out"),
+///     _ => Ok(index * 2),
+/// };
+///
+/// let (sum, errors) = par_bridge_retry(5, 3, 100, fetch, |seq_iter| seq_iter.sum::<usize>());
+///
+/// assert_eq!(sum, 9900);
+/// assert!(errors.is_empty());
+/// ```
+pub fn par_bridge_retry<T, E, Op, F, R>(
+    bound: usize,
+    max_retries: usize,
+    len: usize,
+    op: Op,
+    f: F,
+) -> (R, Vec<(usize, E)>)
+where
+    T: Send,
+    E: Send,
+    Op: Fn(usize) -> Result<T, E> + Sync + Send,
+    F: FnOnce(RayonIntoIter<T>) -> R,
+{
+    let mut failed = Vec::new();
+    let result = bridge(
+        bound,
+        |send| {
+            let mut pending: Vec<_> = (0..len).collect();
+            for _ in 0..=max_retries {
+                let pass_failed = Mutex::new(Vec::new());
+                let stopped = pending
+                    .into_par_iter()
+                    .try_for_each(|index| match op(index) {
+                        Ok(x) => send.send(x).ok(),
+                        Err(e) => {
+                            pass_failed.lock().unwrap().push((index, e));
+                            Some(())
+                        }
+                    })
+                    .is_none();
+
+                failed = pass_failed.into_inner().unwrap();
+                if stopped || failed.is_empty() {
+                    break;
+                }
+                pending = failed.iter().map(|&(index, _)| index).collect();
+            }
+        },
+        f,
+    );
+
+    failed.sort_unstable_by_key(|&(index, _)| index);
+    (result, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn failed_indices_are_retried() {
+        let attempts = (0..1000).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+        let op = |index: usize| {
+            let attempt = attempts[index].fetch_add(1, Ordering::Relaxed);
+            match index {
+                // Never succeeds.
+                999 => Err(attempt),
+                index if index % 7 == 0 && attempt == 0 => Err(attempt),
+                index => Ok(index),
+            }
+        };
+
+        let (mut result, errors) =
+            par_bridge_retry(5, 2, 1000, op, |seq_iter| seq_iter.collect::<Vec<_>>());
+
+        result.sort_unstable();
+        assert_eq!(result, (0..999).collect::<Vec<_>>());
+        assert_eq!(errors, [(999, 2)]);
+        for (index, attempts) in attempts.iter().enumerate() {
+            let expected = match index {
+                999 => 3,
+                index if index % 7 == 0 => 2,
+                _ => 1,
+            };
+            assert_eq!(attempts.load(Ordering::Relaxed), expected, "index {index}");
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::{par_bridge, RayonIntoIter};
+
+/// Same as [`par_bridge`] but lends `f` the last `window` items received along with each new
+/// one, to compute rolling statistics.
+///
+/// Every call to [`RayonWindowIter::next`] receives an item and returns the slice of the most
+/// recent items, oldest first and ending with the new one, which holds up to `window` items.
+/// The older items are dropped as the window slides, so the memory used does not depend on the
+/// length of the stream. The items are in the order they are received, use
+/// [`par_bridge_ordered`](crate::par_bridge_ordered) beforehand to get them in input order.
+///
+/// # Panics
+///
+/// Panics if `window` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_window;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let peaks = par_bridge_window(5, 3, data.into_par_iter(), |mut windows| {
+///     let mut peaks = 0;
+///     while let Some(window) = windows.next() {
+///         assert!(window.len() <= 3);
+///         peaks += window.iter().max().copied().unwrap_or(0);
+///     }
+///     peaks
+/// });
+///
+/// assert!(peaks >= 4950);
+/// ```
+pub fn par_bridge_window<I, F, R>(bound: usize, window: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonWindowIter<I::Item>) -> R,
+{
+    assert!(window != 0, "the window must hold at least one item");
+    par_bridge(bound, iter, |seq_iter| {
+        f(RayonWindowIter { inner: seq_iter, items: VecDeque::with_capacity(window), window })
+    })
+}
+
+/// Lends the most recent items received from a parallel rayon pipeline,
+/// see [`par_bridge_window`].
+///
+/// It cannot implement [`Iterator`] as the slices it returns borrow from it.
+pub struct RayonWindowIter<T> {
+    inner: RayonIntoIter<T>,
+    items: VecDeque<T>,
+    window: usize,
+}
+
+impl<T> RayonWindowIter<T> {
+    /// Receives the next item and returns the window ending with it,
+    /// or `None` once the parallel pipeline is done.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&[T]> {
+        let item = self.inner.next()?;
+        if self.items.len() == self.window {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+        Some(self.items.make_contiguous())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn rolling_average() {
+        // Sent in order so that the expected windows are known.
+        let data = (0u64..1000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().copied().with_min_len(usize::MAX);
+        let averages = par_bridge_window(4, 10, parallel_pipeline, |mut windows| {
+            let mut averages = Vec::new();
+            while let Some(window) = windows.next() {
+                averages.push(window.iter().sum::<u64>() as f64 / window.len() as f64);
+            }
+            assert!(windows.items.len() <= 10);
+            averages
+        });
+
+        assert_eq!(averages.len(), 1000);
+        for (i, average) in averages.into_iter().enumerate() {
+            let expected = &data[i.saturating_sub(9)..=i];
+            let expected = expected.iter().sum::<u64>() as f64 / expected.len() as f64;
+            assert_eq!(average, expected, "window ending at {i}");
+        }
+    }
+}
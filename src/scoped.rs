@@ -0,0 +1,134 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{Scope, ScopedJoinHandle};
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::channel::{self, IntoIter, Lent};
+use crate::{ensure_returned, send_all, with_context, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but runs the consumer on a thread of `scope`
+/// too, and returns its handle instead of blocking the caller.
+///
+/// Both the thread driving the parallel pipeline and the one running `f` are spawned in the
+/// given scope, which lets the caller do other work, or start other bridges, in the meantime.
+/// Joining the handle gives the result of `f`. The scope joins both threads when it ends, if
+/// the handle was not joined before.
+///
+/// # Panics
+///
+/// The panics behave like the ones of [`par_bridge`](crate::par_bridge), except that they are
+/// resumed on the consumer thread: joining the handle returns them as an error.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use rayon_par_bridge::par_bridge_scope;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let sum = thread::scope(|s| {
+///     let handle = par_bridge_scope(s, 5, data.par_iter(), |seq_iter| seq_iter.sum::<u32>());
+///     // The current thread is free to do something else here.
+///     handle.join().unwrap()
+/// });
+///
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_scope<'scope, 'env, I, F, R>(
+    scope: &'scope Scope<'scope, 'env>,
+    bound: usize,
+    iter: I,
+    f: F,
+) -> ScopedJoinHandle<'scope, R>
+where
+    I: IntoParallelIterator + Send + 'scope,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R + Send + 'scope,
+    R: Send + 'scope,
+{
+    let (send, recv) = channel::bounded(bound);
+    let producer = scope.spawn(move || {
+        panic::catch_unwind(AssertUnwindSafe(|| send_all(iter.into_par_iter(), &send)))
+            .map_err(|payload| with_context(Some(bound), payload))
+    });
+
+    scope.spawn(move || {
+        // Same as `bridge_with`, the receiver is dropped with `f` before we wait for the
+        // producer, and the consumer panic wins over the producer one.
+        let _consuming = recv.consuming();
+        let lent = Lent::default();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            f(RayonIntoIter::new(IntoIter::new(recv, &lent)))
+        }))
+        .map(|result| ensure_returned(&lent, result));
+        let produced = producer.join();
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        match produced {
+            Ok(Err(payload)) | Err(payload) => panic::resume_unwind(payload),
+            Ok(Ok(())) => result,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::par_bridge;
+    use crate::tests::with_timeout;
+
+    #[test]
+    fn two_bridges_in_one_scope() {
+        let numbers = (0u32..1000).collect::<Vec<_>>();
+        let words = (0u32..1000).map(|num| num.to_string()).collect::<Vec<_>>();
+
+        let (sum, len, caller) = thread::scope(|s| {
+            let sum = par_bridge_scope(s, 5, numbers.par_iter(), |seq_iter| seq_iter.sum::<u32>());
+            let len = par_bridge_scope(s, 5, words.par_iter(), |seq_iter| {
+                seq_iter.map(String::len).sum::<usize>()
+            });
+            // Both bridges run while the caller thread does its own work.
+            let caller = numbers.iter().filter(|&&num| num % 2 == 0).count();
+            (sum.join().unwrap(), len.join().unwrap(), caller)
+        });
+
+        assert_eq!(sum, 499_500);
+        assert_eq!(len, 2890);
+        assert_eq!(caller, 500);
+    }
+
+    #[test]
+    fn producer_panic_is_returned_by_join() {
+        let payload = thread::scope(|s| {
+            let parallel_pipeline = (0u32..100).into_par_iter().inspect(|&num| {
+                if num == 50 {
+                    panic!("boom");
+                }
+            });
+            par_bridge_scope(s, 5, parallel_pipeline, Iterator::count).join().unwrap_err()
+        });
+
+        let message = payload.downcast::<String>().unwrap();
+        assert!(message.ends_with("boom"), "{message}");
+    }
+
+    #[test]
+    fn nested_bridge_in_the_consumer() {
+        let sum: u64 = with_timeout(|| {
+            thread::scope(|s| {
+                par_bridge_scope(s, 1, (0u64..8).into_par_iter(), |seq_iter| {
+                    seq_iter
+                        .map(|_| par_bridge(1, (0..8).into_par_iter(), Iterator::sum::<u64>))
+                        .sum()
+                })
+                .join()
+                .unwrap()
+            })
+        });
+        assert_eq!(sum, 8 * 28);
+    }
+}
@@ -0,0 +1,119 @@
+use std::ops::ControlFlow;
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, IntoIter, Lent};
+use crate::{bridge_with, RayonIntoIter};
+
+/// An output of the parallel pipeline given to [`par_bridge_with_side`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeOut<T, M> {
+    /// An item of the main stream.
+    Item(T),
+    /// Out-of-band metadata, like a warning or a skipped record notice.
+    Meta(M),
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but splits the outputs of the parallel pipeline
+/// into the main items and out-of-band metadata, delivered through two separate iterators.
+///
+/// The items go through a channel of `bound` items, which keeps the backpressure on the
+/// parallel pipeline as usual. The metadata is meant to be rare and goes through an unbounded
+/// channel, it never blocks the Rayon workers and can be read before, after or while the items
+/// are. Both iterators end once the parallel pipeline is done. Dropping the items iterator
+/// stops the parallel pipeline, dropping the metadata one only discards the metadata.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::{par_bridge_with_side, BridgeOut};
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| match num % 10 {
+///     0 => BridgeOut::Meta(format!("skipped {num}")),
+///     _ => BridgeOut::Item(num),
+/// });
+///
+/// let (count, warnings) = par_bridge_with_side(5, parallel_pipeline, |items, meta| {
+///     (items.count(), meta.collect::<Vec<_>>())
+/// });
+///
+/// assert_eq!(count, 90);
+/// assert_eq!(warnings.len(), 10);
+/// ```
+pub fn par_bridge_with_side<I, T, M, F, R>(bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator<Item = BridgeOut<T, M>> + Send,
+    T: Send,
+    M: Send,
+    F: FnOnce(RayonIntoIter<T>, RayonIntoIter<M>) -> R,
+{
+    let (send_meta, recv_meta) = channel::unbounded();
+    let lent = Lent::default();
+    bridge_with(
+        thread::Builder::new(),
+        channel::bounded(bound),
+        move |send| {
+            // Both senders are dropped once the pipeline is done, closing the two channels.
+            let _ = iter.into_par_iter().try_for_each(|out| match out {
+                BridgeOut::Item(x) => match send.send(x) {
+                    Ok(()) => ControlFlow::Continue(()),
+                    Err(_) => ControlFlow::Break(()),
+                },
+                BridgeOut::Meta(meta) => {
+                    // Nobody reads the metadata anymore, the items may still be wanted.
+                    let _ = send_meta.send(meta);
+                    ControlFlow::Continue(())
+                }
+            });
+        },
+        |recv| {
+            let meta = RayonIntoIter::new(IntoIter::new(recv_meta, &lent));
+            f(RayonIntoIter::new(recv), meta)
+        },
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn both_streams_are_delivered() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().map(|&num| match num % 100 {
+            0 => BridgeOut::Meta(num),
+            _ => BridgeOut::Item(num),
+        });
+
+        let (mut items, mut meta) = par_bridge_with_side(4, parallel_pipeline, |items, meta| {
+            (items.collect::<Vec<_>>(), meta.collect::<Vec<_>>())
+        });
+
+        items.sort_unstable();
+        meta.sort_unstable();
+        assert_eq!(items, data.iter().copied().filter(|num| num % 100 != 0).collect::<Vec<_>>());
+        assert_eq!(meta, data.iter().copied().filter(|num| num % 100 == 0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dropped_metadata_keeps_the_items_flowing() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.par_iter().map(|&num| match num % 2 {
+            0 => BridgeOut::Meta(num),
+            _ => BridgeOut::Item(num),
+        });
+
+        let count = par_bridge_with_side(4, parallel_pipeline, |items, meta| {
+            drop(meta);
+            items.count()
+        });
+
+        assert_eq!(count, 5_000);
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{process, thread};
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::bridge_with;
+use crate::channel::{self, IntoIter};
+
+/// Same as [`par_bridge_ordered`](crate::par_bridge_ordered) but keeps at most `mem_limit`
+/// out-of-order items in memory and spills the other ones to a temporary file.
+///
+/// Unlike the ordered bridge, the Rayon workers never wait for the consumer to catch up with a
+/// slow early item: they keep sending items, and the ones that run too far ahead are written to
+/// the file with `encode` and read back with `decode` when their turn comes. The items are
+/// yielded in strict input order with a bounded amount of memory, at most `mem_limit` items
+/// waiting for their turn plus `mem_limit` items in the channel. The items kept in memory are
+/// the closest to be yielded, the ones furthest ahead are spilled first.
+///
+/// The file is created in [`std::env::temp_dir`] on the first spill and removed when the
+/// iterator is dropped. The space of the items read back is only reused once every spilled item
+/// has been read back.
+///
+/// This is only available with the `spill` feature.
+///
+/// # Panics
+///
+/// Panics if the temporary file cannot be created, written or read.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_ordered_spill;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let encode = |num: &u32, buf: &mut Vec<u8>| buf.extend_from_slice(&num.to_le_bytes());
+/// let decode = |buf: &[u8]| u32::from_le_bytes(buf.try_into().unwrap());
+///
+/// let result: Vec<_> =
+///     par_bridge_ordered_spill(4, encode, decode, data.into_par_iter(), |seq_iter| seq_iter.collect());
+///
+/// assert_eq!(result, (0u32..100).collect::<Vec<_>>());
+/// ```
+pub fn par_bridge_ordered_spill<I, E, D, F, R>(
+    mem_limit: usize,
+    encode: E,
+    decode: D,
+    iter: I,
+    f: F,
+) -> R
+where
+    I: IntoParallelIterator + Send,
+    I::Iter: IndexedParallelIterator,
+    E: FnMut(&I::Item, &mut Vec<u8>),
+    D: FnMut(&[u8]) -> I::Item,
+    F: FnOnce(SpillRayonIter<I::Item, E, D>) -> R,
+{
+    bridge_with(
+        thread::Builder::new(),
+        channel::bounded(mem_limit),
+        |send| {
+            iter.into_par_iter().enumerate().try_for_each(|item| send.send(item).ok());
+        },
+        |recv| {
+            f(SpillRayonIter {
+                recv,
+                next: 0,
+                mem_limit,
+                pending: BTreeMap::new(),
+                spilled: HashMap::new(),
+                file: None,
+                encode,
+                decode,
+                buf: Vec::new(),
+            })
+        },
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+/// An `Iterator` over the elements returned by an indexed parallel rayon pipeline, in their
+/// original order, see [`par_bridge_ordered_spill`].
+pub struct SpillRayonIter<T, E, D> {
+    recv: IntoIter<(usize, T)>,
+    /// The index of the next item to be yielded.
+    next: usize,
+    mem_limit: usize,
+    /// The out-of-order items kept in memory.
+    pending: BTreeMap<usize, T>,
+    /// The position and length in the file of the out-of-order items spilled.
+    spilled: HashMap<usize, (u64, usize)>,
+    file: Option<SpillFile>,
+    encode: E,
+    decode: D,
+    /// Reused to encode and decode the spilled items.
+    buf: Vec<u8>,
+}
+
+impl<T, E, D> SpillRayonIter<T, E, D>
+where
+    E: FnMut(&T, &mut Vec<u8>),
+    D: FnMut(&[u8]) -> T,
+{
+    fn spill(&mut self, index: usize, x: T) {
+        self.buf.clear();
+        (self.encode)(&x, &mut self.buf);
+        drop(x);
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(SpillFile::create().expect("failed to create the spill file")),
+        };
+        let position = file.append(&self.buf).expect("failed to write to the spill file");
+        self.spilled.insert(index, (position, self.buf.len()));
+    }
+
+    fn unspill(&mut self, position: u64, len: usize) -> T {
+        let file = self.file.as_mut().expect("the items were spilled to a file");
+        self.buf.resize(len, 0);
+        file.read_at(position, &mut self.buf).expect("failed to read from the spill file");
+        if self.spilled.is_empty() {
+            file.clear().expect("failed to truncate the spill file");
+        }
+        (self.decode)(&self.buf)
+    }
+}
+
+impl<T, E, D> Iterator for SpillRayonIter<T, E, D>
+where
+    E: FnMut(&T, &mut Vec<u8>),
+    D: FnMut(&[u8]) -> T,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.next;
+            if let Some(x) = self.pending.remove(&next) {
+                self.next += 1;
+                return Some(x);
+            }
+            if let Some((position, len)) = self.spilled.remove(&next) {
+                self.next += 1;
+                return Some(self.unspill(position, len));
+            }
+
+            // If the producer stops before sending the next index there is
+            // a gap we can never fill, the stream ends there.
+            let (i, x) = self.recv.recv(true).ok()?;
+            if i == next {
+                self.next += 1;
+                return Some(x);
+            }
+            if self.pending.len() < self.mem_limit {
+                self.pending.insert(i, x);
+                continue;
+            }
+            // Keeps the items that will be yielded first in memory.
+            match self.pending.last_key_value() {
+                Some((&last, _)) if last > i => {
+                    let (last, spilled) = self.pending.pop_last().unwrap();
+                    self.pending.insert(i, x);
+                    self.spill(last, spilled);
+                }
+                _ => self.spill(i, x),
+            }
+        }
+    }
+}
+
+/// The temporary file the out-of-order items are spilled to, removed when dropped.
+struct SpillFile {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl SpillFile {
+    fn create() -> io::Result<SpillFile> {
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        let n = CREATED.fetch_add(1, Ordering::Relaxed);
+        let name = format!("rayon-par-bridge-{}-{n}.spill", process::id());
+        let path = std::env::temp_dir().join(name);
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        Ok(SpillFile { file, path, len: 0 })
+    }
+
+    /// Writes `bytes` at the end of the file and returns their position.
+    fn append(&mut self, bytes: &[u8]) -> io::Result<u64> {
+        let position = self.len;
+        self.file.seek(SeekFrom::Start(position))?;
+        self.file.write_all(bytes)?;
+        self.len += bytes.len() as u64;
+        Ok(position)
+    }
+
+    fn read_at(&mut self, position: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(position))?;
+        self.file.read_exact(buf)
+    }
+
+    /// Discards the content of the file, once every item spilled was read back.
+    fn clear(&mut self) -> io::Result<()> {
+        self.len = 0;
+        self.file.set_len(0)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::Yield;
+
+    use super::*;
+
+    fn encode(num: &u64, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&num.to_le_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> u64 {
+        u64::from_le_bytes(buf.try_into().unwrap())
+    }
+
+    #[test]
+    fn slow_early_item_spills_to_disk() {
+        let processed = AtomicUsize::new(0);
+        let data = (0u64..10_000).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().map(|num| {
+            // The first item waits for a thousand others, run by the same worker if need be.
+            if num == 0 {
+                while processed.load(Ordering::Relaxed) < 1000 {
+                    if rayon::yield_now() != Some(Yield::Executed) {
+                        thread::yield_now();
+                    }
+                }
+            } else {
+                processed.fetch_add(1, Ordering::Relaxed);
+            }
+            num
+        });
+
+        let (result, path, peak) =
+            par_bridge_ordered_spill(8, encode, decode, parallel_pipeline, |mut seq_iter| {
+                let mut result = vec![seq_iter.next().unwrap()];
+                let path = seq_iter.file.as_ref().map(|file| file.path.clone());
+                assert!(path.as_ref().is_some_and(|path| path.exists()), "nothing was spilled");
+
+                let mut peak = 0;
+                while let Some(num) = seq_iter.next() {
+                    peak = peak.max(seq_iter.pending.len());
+                    result.push(num);
+                }
+                (result, path.unwrap(), peak)
+            });
+
+        assert_eq!(result, (0u64..10_000).collect::<Vec<_>>());
+        assert!(peak <= 8, "{peak} items were kept in memory");
+        assert!(!path.exists(), "the spill file was not removed");
+    }
+}
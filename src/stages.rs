@@ -0,0 +1,207 @@
+use std::iter::Map;
+
+use rayon::iter::{IntoParallelIterator, IterBridge, ParallelBridge, ParallelIterator};
+use rayon::ThreadPoolBuilder;
+
+use crate::{bridge, par_bridge, RayonIntoIter};
+
+/// Runs a parallel pipeline, a sequential step and another parallel pipeline one after the
+/// other, streaming the items between them.
+///
+/// The items of `iter` are passed to `seq` one at a time, in the order they are received, and
+/// its outputs are turned back into a parallel iterator with Rayon's
+/// [`par_bridge`](rayon::iter::ParallelBridge::par_bridge) and handed to `then_par`. This
+/// suits a sequential step in the middle of a parallel pipeline, like assigning increasing
+/// identifiers to the items.
+///
+/// The workers of the second pipeline wait for the items of the first one, if both ran on the
+/// same pool they could take every thread and leave none to produce the items. The second
+/// pipeline runs on a dedicated pool with as many threads as the current one, built for the
+/// call, and `seq` runs on its workers, under a lock.
+///
+/// # Panics
+///
+/// Panics if the pool of the second pipeline could not be built.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_then_par;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let mut next_id = 0;
+/// let mut result: Vec<(u32, u32)> = par_bridge_then_par(
+///     5,
+///     data.into_par_iter().map(|num| num * 2),
+///     |num| {
+///         next_id += 1;
+///         (next_id, num)
+///     },
+///     |par_iter| par_iter.collect(),
+/// );
+///
+/// result.sort_unstable();
+/// assert!(result.iter().map(|&(id, _)| id).eq(1..=100));
+/// ```
+pub fn par_bridge_then_par<I, F, U, G, R>(bound: usize, iter: I, seq: F, then_par: G) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnMut(I::Item) -> U + Send,
+    U: Send,
+    G: FnOnce(IterBridge<Map<RayonIntoIter<I::Item>, F>>) -> R + Send,
+    R: Send,
+{
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(rayon::current_num_threads())
+        .build()
+        .expect("failed to build the pool of the second pipeline");
+    par_bridge(bound, iter, |seq_iter| pool.install(|| then_par(seq_iter.map(seq).par_bridge())))
+}
+
+/// Processes the items of a sequential iterator in parallel and consumes the results
+/// sequentially, without ever holding the whole input or output in memory.
+///
+/// The Rayon workers pull the items of `input` one at a time, with Rayon's
+/// [`par_bridge`](rayon::iter::ParallelBridge::par_bridge), call `process` on them and send
+/// the results to `f` through a channel of `bound` items, as [`par_bridge`] does. Only the
+/// items being processed and the ones waiting in the channel are in memory at any time. The
+/// consumer sees the end of the stream once `input` is exhausted, and if it stops early, the
+/// workers stop pulling from `input`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_pipe;
+///
+/// let lines = (0u64..1000).map(|num| num.to_string());
+/// let sum = par_pipe(5, lines, |line| line.parse::<u64>().unwrap() * 2, |seq_iter| {
+///     seq_iter.sum::<u64>()
+/// });
+///
+/// assert_eq!(sum, 999_000);
+/// ```
+pub fn par_pipe<In, Out, P, F, R>(bound: usize, input: In, process: P, f: F) -> R
+where
+    In: Iterator + Send,
+    In::Item: Send,
+    P: Fn(In::Item) -> Out + Sync + Send,
+    Out: Send,
+    F: FnOnce(RayonIntoIter<Out>) -> R,
+{
+    par_bridge(bound, input.par_bridge().map(process), f)
+}
+
+/// Same as [`par_bridge`] but relays the items through a second channel of `stage_bound` items,
+/// so that a slow step in `f` does not immediately stall the parallel pipeline.
+///
+/// A relay thread moves the items from the channel filled by the Rayon workers to the one `f`
+/// drains, and closes the latter once the parallel pipeline is done. The workers only wait for
+/// `f` once both channels are full. Dropping the iterator still stops the parallel pipeline,
+/// the relay forwards the disconnection.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_staged;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let result: Vec<_> = par_bridge_staged(5, 50, data.into_par_iter(), |seq_iter| {
+///     seq_iter.map(|num| num * 2).collect()
+/// });
+///
+/// assert_eq!(result.len(), 100);
+/// ```
+pub fn par_bridge_staged<I, F, R>(bound: usize, stage_bound: usize, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    par_bridge(bound, iter, |seq_iter| {
+        bridge(
+            stage_bound,
+            move |send| {
+                for item in seq_iter {
+                    if send.send(item).is_err() {
+                        break;
+                    }
+                }
+            },
+            f,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn increasing_identifiers() {
+        let data = (0u64..10_000).collect::<Vec<_>>();
+        let in_seq = AtomicBool::new(false);
+        let mut next_id = 0;
+
+        let mut result: Vec<_> = par_bridge_then_par(
+            5,
+            data.par_iter().map(|num| num * 2),
+            |num| {
+                assert!(!in_seq.swap(true, Ordering::SeqCst), "concurrent calls to seq");
+                let id = next_id;
+                next_id += 1;
+                in_seq.store(false, Ordering::SeqCst);
+                (id, num)
+            },
+            |par_iter| par_iter.map(|(id, num)| (id, num + 1)).collect(),
+        );
+
+        result.sort_unstable();
+        assert!(result.iter().map(|&(id, _)| id).eq(0..10_000));
+        let mut values: Vec<_> = result.into_iter().map(|(_, num)| num).collect();
+        values.sort_unstable();
+        assert!(values.into_iter().eq(data.iter().map(|num| num * 2 + 1)));
+    }
+
+    #[test]
+    fn pipe_doubles_every_line() {
+        let lines = (0u64..100_000).map(|num| num.to_string());
+        let mut result: Vec<_> =
+            par_pipe(5, lines, |line| line.parse::<u64>().unwrap() * 2, Iterator::collect);
+
+        result.sort_unstable();
+        assert!(result.into_iter().eq((0..100_000).map(|num| num * 2)));
+    }
+
+    #[test]
+    fn staged_relay_closes_the_second_channel() {
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let (mut result, exhausted) =
+            par_bridge_staged(4, 64, data.par_iter().copied(), |mut seq_iter| {
+                let result: Vec<_> = seq_iter.by_ref().collect();
+                (result, seq_iter.next().is_none())
+            });
+
+        assert!(exhausted);
+        result.sort_unstable();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn staged_early_drop_stops_the_producer() {
+        let processed = AtomicUsize::new(0);
+        let parallel_pipeline = (0u32..1_000_000).into_par_iter().inspect(|_| {
+            processed.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let count =
+            par_bridge_staged(1, 16, parallel_pipeline, |seq_iter| seq_iter.take(5).count());
+
+        assert_eq!(count, 5);
+        assert!(processed.load(Ordering::Relaxed) < 10_000);
+    }
+}
@@ -0,0 +1,393 @@
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{bridge, RayonIntoIter};
+
+/// Statistics about a bridge run, returned by [`par_bridge_with_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BridgeStats {
+    /// The number of items successfully sent by the parallel pipeline.
+    pub produced: usize,
+    /// The number of items the consumer actually pulled from the iterator.
+    pub delivered: usize,
+    /// Whether the parallel iterator was fully consumed.
+    pub completed: bool,
+    /// The maximum number of items waiting in the channel at the same time.
+    ///
+    /// When it often reaches the bound, the consumer is the bottleneck,
+    /// when it stays near zero, the producer is.
+    pub peak_depth: usize,
+    /// The total time the Rayon workers spent waiting for room in the channel.
+    ///
+    /// When it dominates `consumer_blocked`, the consumer is the bottleneck.
+    pub producer_blocked: Duration,
+    /// The total time the consumer spent waiting for an item.
+    ///
+    /// When it dominates `producer_blocked`, the parallel pipeline is the bottleneck.
+    pub consumer_blocked: Duration,
+}
+
+/// The counters shared between the producer and the consumer.
+#[derive(Default)]
+pub(crate) struct Counters {
+    produced: AtomicUsize,
+    delivered: AtomicUsize,
+    completed: AtomicBool,
+    /// The number of items in the channel, counted after they are sent and before they are
+    /// received, so that it never exceeds the real number. It is negative while the consumer
+    /// waits for an item.
+    depth: AtomicIsize,
+    peak_depth: AtomicIsize,
+    /// The time spent blocked on the channel, in nanoseconds, summed over every worker.
+    producer_blocked: AtomicU64,
+    consumer_blocked: AtomicU64,
+}
+
+impl Counters {
+    fn sent(&self) {
+        self.produced.fetch_add(1, Ordering::Relaxed);
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// Must be called before the consumer tries to receive an item.
+    pub(crate) fn receiving(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Must be called when the consumer found the channel empty and did not wait for an item.
+    pub(crate) fn gave_up(&self) {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn delivered(&self) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Must be called with the time the consumer spent in a blocking receive.
+    pub(crate) fn consumer_waited(&self, duration: Duration) {
+        self.consumer_blocked.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> BridgeStats {
+        BridgeStats {
+            produced: self.produced.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            peak_depth: self.peak_depth.load(Ordering::Relaxed).max(0) as usize,
+            producer_blocked: Duration::from_nanos(self.producer_blocked.load(Ordering::Relaxed)),
+            consumer_blocked: Duration::from_nanos(self.consumer_blocked.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but also returns statistics about the run.
+///
+/// When the consumer stops early, the difference between the `produced` and `delivered`
+/// counts tells how many items were computed for nothing, and `completed` tells whether
+/// the parallel iterator was fully consumed. Only the blocking calls on the channel are timed,
+/// not the work of the parallel pipeline or of the consumer.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_with_stats;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let (result, stats) =
+///     par_bridge_with_stats(5, parallel_pipeline, |seq_iter| seq_iter.collect::<Vec<_>>());
+///
+/// assert_eq!(result.len(), 100);
+/// assert_eq!(stats.produced, 100);
+/// assert_eq!(stats.delivered, 100);
+/// assert!(stats.completed);
+/// ```
+pub fn par_bridge_with_stats<I, F, R>(bound: usize, iter: I, f: F) -> (R, BridgeStats)
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let counters = Arc::new(Counters::default());
+    let result = bridge(
+        bound,
+        |send| {
+            let completed = iter.into_par_iter().try_for_each(|x| {
+                let started = Instant::now();
+                let sent = send.send(x);
+                let waited = started.elapsed().as_nanos() as u64;
+                counters.producer_blocked.fetch_add(waited, Ordering::Relaxed);
+                sent.ok()?;
+                counters.sent();
+                Some(())
+            });
+            counters.completed.store(completed.is_some(), Ordering::Relaxed);
+        },
+        |recv| f(recv.with_counters(counters.clone())),
+    );
+
+    (result, counters.stats())
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but also returns the number of items sent
+/// by the parallel pipeline.
+///
+/// The count is the number of items that went through the channel, which can differ from the
+/// input length when the pipeline filters items. If the consumer stops early, it includes the
+/// items that were sent but never pulled from the iterator.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_counted;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().filter(|num| num % 2 == 0);
+///
+/// let (sum, count) = par_bridge_counted(5, parallel_pipeline, |seq_iter| seq_iter.sum::<u32>());
+///
+/// assert_eq!(sum, 2450);
+/// assert_eq!(count, 50);
+/// ```
+pub fn par_bridge_counted<I, F, R>(bound: usize, iter: I, f: F) -> (R, usize)
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let produced = AtomicUsize::new(0);
+    let result = bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                send.send(x).ok()?;
+                produced.fetch_add(1, Ordering::Relaxed);
+                Some(())
+            });
+        },
+        f,
+    );
+
+    (result, produced.into_inner())
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but calls `on_produced` with the number of items
+/// produced so far after each item is sent.
+///
+/// The callback runs on the Rayon workers as items are produced, independently of the
+/// consumer, so it must be cheap. As it is called concurrently from many workers, the counts
+/// it sees are not strictly increasing: a worker can report a count after another worker
+/// reported a greater one. The greatest count reported is the total number of items produced.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use rayon_par_bridge::par_bridge_with_progress;
+/// use rayon::prelude::*;
+///
+/// let progress = AtomicUsize::new(0);
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let parallel_pipeline = data.into_par_iter().map(|num| num * 2);
+///
+/// let result: Vec<_> = par_bridge_with_progress(
+///     5,
+///     parallel_pipeline,
+///     |seq_iter| seq_iter.collect(),
+///     |count| {
+///         progress.fetch_max(count, Ordering::Relaxed);
+///     },
+/// );
+///
+/// assert_eq!(progress.into_inner(), result.len());
+/// ```
+pub fn par_bridge_with_progress<I, F, R, P>(bound: usize, iter: I, f: F, on_produced: P) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    P: Fn(usize) + Send + Sync,
+{
+    let produced = AtomicUsize::new(0);
+    bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                send.send(x).ok()?;
+                on_produced(produced.fetch_add(1, Ordering::Relaxed) + 1);
+                Some(())
+            });
+        },
+        f,
+    )
+}
+
+/// Same as [`par_bridge`](crate::par_bridge) but calls `on_latency` with the time each item
+/// spent between being sent by the parallel pipeline and being pulled by the consumer.
+///
+/// The items are timestamped by the Rayon workers and `on_latency` runs on the current thread,
+/// right before each item is yielded to `f`. Growing latencies mean the consumer is lagging
+/// behind the parallel pipeline.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rayon_par_bridge::par_bridge_timed;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let mut max_latency = Duration::ZERO;
+/// let count = par_bridge_timed(5, data.into_par_iter(), Iterator::count, |latency| {
+///     max_latency = max_latency.max(latency);
+/// });
+///
+/// assert_eq!(count, 100);
+/// ```
+pub fn par_bridge_timed<I, F, R, L>(bound: usize, iter: I, f: F, on_latency: L) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(TimedRayonIter<I::Item, L>) -> R,
+    L: FnMut(Duration),
+{
+    bridge(
+        bound,
+        |send| {
+            iter.into_par_iter().try_for_each(|x| send.send((Instant::now(), x)).ok());
+        },
+        |inner| f(TimedRayonIter { inner, on_latency }),
+    )
+}
+
+/// An `Iterator` over the elements returned by a parallel rayon pipeline,
+/// reporting how long each one waited, see [`par_bridge_timed`].
+pub struct TimedRayonIter<T, L> {
+    inner: RayonIntoIter<(Instant, T)>,
+    on_latency: L,
+}
+
+impl<T, L: FnMut(Duration)> Iterator for TimedRayonIter<T, L> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (sent_at, x) = self.inner.next()?;
+        (self.on_latency)(sent_at.elapsed());
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn early_stop() {
+        let data = (0u32..100_000).collect::<Vec<_>>();
+        let (result, stats) = par_bridge_with_stats(5, data.into_par_iter(), |seq_iter| {
+            seq_iter.take(10).collect::<Vec<_>>()
+        });
+
+        assert_eq!(result.len(), 10);
+        assert_eq!(stats.delivered, 10);
+        assert!(stats.produced >= 10);
+        assert!(!stats.completed);
+    }
+
+    #[test]
+    fn slow_consumer_fills_the_channel() {
+        let data = (0u32..200).collect::<Vec<_>>();
+        let (_, stats) = par_bridge_with_stats(4, data.into_par_iter(), |seq_iter| {
+            seq_iter.for_each(|_| thread::sleep(Duration::from_millis(1)))
+        });
+
+        assert_eq!(stats.peak_depth, 4);
+    }
+
+    #[test]
+    fn slow_consumer_blocks_the_producer() {
+        let data = (0u32..200).collect::<Vec<_>>();
+        let (_, stats) = par_bridge_with_stats(4, data.into_par_iter(), |seq_iter| {
+            seq_iter.for_each(|_| thread::sleep(Duration::from_millis(1)))
+        });
+
+        // The consumer sleeps for at least 200ms and the producer waits for most of it.
+        assert!(stats.producer_blocked >= Duration::from_millis(100), "{stats:?}");
+        assert!(stats.producer_blocked > stats.consumer_blocked, "{stats:?}");
+    }
+
+    #[test]
+    fn slow_producer_keeps_the_channel_empty() {
+        // Produces the items one at a time, whatever the number of workers.
+        let lock = Mutex::new(());
+        let data = (0u32..100).collect::<Vec<_>>();
+        let parallel_pipeline = data.into_par_iter().inspect(|_| {
+            let _guard = lock.lock().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        });
+        let (_, stats) = par_bridge_with_stats(4, parallel_pipeline, Iterator::count);
+
+        assert!(stats.peak_depth <= 1);
+    }
+
+    #[test]
+    fn counted_after_a_filter() {
+        let (result, count) =
+            par_bridge_counted(5, (0u32..100).into_par_iter().filter(|n| n % 2 == 0), |seq_iter| {
+                seq_iter.collect::<Vec<_>>()
+            });
+        assert_eq!(result.len(), 50);
+        assert_eq!(count, 50);
+
+        let (result, count) = par_bridge_counted(5, (0u32..100_000).into_par_iter(), |seq_iter| {
+            seq_iter.take(10).count()
+        });
+        assert_eq!(result, 10);
+        assert!(count >= 10);
+    }
+
+    #[test]
+    fn slow_consumer_has_larger_latencies() {
+        fn average_latency(delay: Duration) -> Duration {
+            let mut total = Duration::ZERO;
+            let count = par_bridge_timed(
+                4,
+                (0u32..200).into_par_iter(),
+                |seq_iter| seq_iter.inspect(|_| thread::sleep(delay)).count(),
+                |latency| total += latency,
+            );
+            assert_eq!(count, 200);
+            total / 200
+        }
+
+        let fast = average_latency(Duration::ZERO);
+        let slow = average_latency(Duration::from_millis(1));
+        assert!(slow > fast, "slow: {slow:?}, fast: {fast:?}");
+    }
+
+    #[test]
+    fn progress_reaches_the_total() {
+        let last = AtomicUsize::new(0);
+        let calls = AtomicUsize::new(0);
+        let data = (0u32..10_000).collect::<Vec<_>>();
+        let count = par_bridge_with_progress(5, data.into_par_iter(), Iterator::count, |count| {
+            last.fetch_max(count, Ordering::Relaxed);
+            calls.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert_eq!(count, 10_000);
+        assert_eq!(last.into_inner(), 10_000);
+        assert_eq!(calls.into_inner(), 10_000);
+    }
+}
@@ -0,0 +1,146 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use futures_core::Stream;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, Receiver, TryRecvError};
+
+/// Transforms a Rayon parallel iterator into an asynchronous stream of its items.
+///
+/// The parallel pipeline is driven from a dedicated thread, and waiting for the next item
+/// yields to the executor instead of blocking it: the task is woken up once an item is sent.
+/// When the channel is full, the Rayon workers wait for the stream to be polled, `bound` being
+/// the maximum number of items waiting to be consumed.
+///
+/// As the stream can outlive the current function, the parallel iterator must be `'static`.
+/// Dropping the stream stops the parallel pipeline, and a panic in the parallel pipeline is
+/// resumed by the poll that would have ended the stream. A `bound` of zero behaves like a
+/// `bound` of one, the items are never handed over directly from a Rayon worker to the task.
+///
+/// This is only available with the `futures` feature, [`ParStream`] implements the
+/// [`Stream`](futures_core::Stream) trait of `futures-core`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_stream;
+/// use rayon::prelude::*;
+/// use tokio_stream::StreamExt;
+///
+/// async fn sum() -> u32 {
+///     let data = (0u32..100).collect::<Vec<_>>();
+///     let mut stream = par_stream(5, data.into_par_iter().map(|num| num * 2));
+///
+///     let mut sum = 0;
+///     while let Some(num) = stream.next().await {
+///         sum += num;
+///     }
+///     sum
+/// }
+/// ```
+pub fn par_stream<I>(bound: usize, iter: I) -> ParStream<I::Item>
+where
+    I: IntoParallelIterator + Send + 'static,
+{
+    let (send, recv) = channel::bounded(bound.max(1));
+    let shared = Arc::new(Shared { waker: Mutex::new(None), panic: Mutex::new(None) });
+
+    let producer = shared.clone();
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            iter.into_par_iter().try_for_each(|x| {
+                send.send(x).ok()?;
+                producer.wake();
+                Some(())
+            });
+        }));
+        if let Err(payload) = result {
+            *producer.panic.lock().unwrap() = Some(payload);
+        }
+        // The channel is disconnected before the last wake up, so that the task sees the end.
+        drop(send);
+        producer.wake();
+    });
+
+    ParStream { recv, shared }
+}
+
+/// An asynchronous stream over the elements returned by a parallel rayon pipeline.
+///
+/// Dropping it stops the parallel pipeline, like dropping a
+/// [`RayonIntoIter`](crate::RayonIntoIter).
+pub struct ParStream<T> {
+    recv: Receiver<T>,
+    shared: Arc<Shared>,
+}
+
+impl<T> ParStream<T> {
+    fn try_next(&self) -> Poll<Option<T>> {
+        match self.recv.try_recv() {
+            Ok(item) => Poll::Ready(Some(item)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => match self.shared.panic.lock().unwrap().take() {
+                Some(payload) => panic::resume_unwind(payload),
+                None => Poll::Ready(None),
+            },
+        }
+    }
+}
+
+impl<T> Stream for ParStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Poll::Ready(item) = self.try_next() {
+            return Poll::Ready(item);
+        }
+
+        // An item sent before the waker is stored is seen by the second try, one sent after
+        // wakes the task up.
+        *self.shared.waker.lock().unwrap() = Some(cx.waker().clone());
+        self.try_next()
+    }
+}
+
+/// What the thread driving the parallel pipeline shares with the stream.
+struct Shared {
+    /// The task waiting for an item.
+    waker: Mutex<Option<Waker>>,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+impl Shared {
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::par_bridge;
+
+    #[tokio::test]
+    async fn matches_sync_bridge() {
+        let data = (0u32..1000).collect::<Vec<_>>();
+        let mut expected: Vec<_> =
+            par_bridge(5, data.par_iter().map(|num| num * 2), |seq_iter| seq_iter.collect());
+
+        let stream = par_stream(5, data.into_par_iter().map(|num| num * 2));
+        let mut result: Vec<_> = stream.collect().await;
+
+        expected.sort_unstable();
+        result.sort_unstable();
+        assert_eq!(result, expected);
+    }
+}
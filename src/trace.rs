@@ -0,0 +1,77 @@
+//! The spans and events of the `tracing` feature, they compile to nothing without it.
+
+pub(crate) use self::imp::*;
+
+#[cfg(feature = "tracing")]
+mod imp {
+    pub(crate) use tracing::Span;
+
+    /// The target of the spans and events, rather than the path of this module.
+    const TARGET: &str = "rayon_par_bridge";
+
+    /// Opens the span of the thread driving a parallel pipeline, a child of `parent`.
+    ///
+    /// `parent` must be captured on the thread starting the bridge, the span of the calling
+    /// code is not propagated to the spawned thread.
+    pub(crate) fn producer(parent: &Span, bound: Option<usize>) -> Span {
+        tracing::info_span!(
+            target: TARGET,
+            parent: parent,
+            "par_bridge.producer",
+            bound,
+            len = tracing::field::Empty,
+        )
+    }
+
+    /// Opens the span of the consumer of a bridge, on the current thread.
+    pub(crate) fn consumer(bound: Option<usize>) -> Span {
+        tracing::info_span!(target: TARGET, "par_bridge.consumer", bound)
+    }
+
+    /// Records the number of items of the parallel pipeline on the current producer span,
+    /// when the parallel iterator knows it.
+    pub(crate) fn record_len(len: Option<usize>) {
+        if let Some(len) = len {
+            Span::current().record("len", len);
+        }
+    }
+
+    /// Tells that an item was sent through the channel, by a Rayon worker of the pipeline
+    /// driven in `span`.
+    pub(crate) fn sent(span: &Span) {
+        tracing::trace!(target: TARGET, parent: span, "item sent");
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod imp {
+    /// Stands for a span, there is none without the `tracing` feature.
+    #[derive(Clone)]
+    pub(crate) struct Span;
+
+    impl Span {
+        pub(crate) fn current() -> Span {
+            Span
+        }
+
+        pub(crate) fn none() -> Span {
+            Span
+        }
+
+        pub(crate) fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+            f()
+        }
+    }
+
+    pub(crate) fn producer(_parent: &Span, _bound: Option<usize>) -> Span {
+        Span
+    }
+
+    pub(crate) fn consumer(_bound: Option<usize>) -> Span {
+        Span
+    }
+
+    pub(crate) fn record_len(_len: Option<usize>) {}
+
+    pub(crate) fn sent(_span: &Span) {}
+}
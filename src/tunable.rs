@@ -0,0 +1,224 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, IntoIter, TryRecvError};
+use crate::{bridge_with, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but the number of items the channel can hold can
+/// be changed while the bridge runs, through the [`BoundHandle`] given to `setup`.
+///
+/// `setup` is called on the current thread before the parallel pipeline starts, it can keep
+/// the handle, or clones of it, to tune the bound later from `f` or from another thread.
+/// Raising the bound wakes the Rayon workers waiting for room. Lowering it keeps the items
+/// already in flight, the workers only wait until their number drops below the new bound.
+///
+/// Unlike [`par_bridge`](crate::par_bridge), a bound of zero does not hand the items over one at
+/// a time: no item can be sent until the bound is raised, which pauses the parallel pipeline.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+///
+/// use rayon_par_bridge::par_bridge_tunable;
+/// use rayon::prelude::*;
+///
+/// let data = (0u32..100).collect::<Vec<_>>();
+/// let handle = Cell::new(None);
+/// let setup = |bound| handle.set(Some(bound));
+/// let sum = par_bridge_tunable(0, data.into_par_iter(), setup, |seq_iter| {
+///     // The workers are paused until there is some room.
+///     handle.take().unwrap().set(16);
+///     seq_iter.sum::<u32>()
+/// });
+///
+/// assert_eq!(sum, 4950);
+/// ```
+pub fn par_bridge_tunable<I, S, F, R>(bound: usize, iter: I, setup: S, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    S: FnOnce(BoundHandle),
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+{
+    let limit = Arc::new(Limit::new(bound));
+    setup(BoundHandle { limit: limit.clone() });
+
+    let producer_limit = limit.clone();
+    bridge_with(
+        thread::Builder::new(),
+        channel::unbounded(),
+        move |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                producer_limit.acquire()?;
+                send.send(x).ok()
+            });
+        },
+        |recv| f(RayonIntoIter::tunable(Tunable { recv, limit })),
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+/// Changes the bound of a running [`par_bridge_tunable`].
+///
+/// The handle can be cloned and sent to other threads. Setting the bound once the bridge is done
+/// has no effect.
+#[derive(Clone)]
+pub struct BoundHandle {
+    limit: Arc<Limit>,
+}
+
+impl BoundHandle {
+    /// Returns the current bound.
+    pub fn get(&self) -> usize {
+        self.limit.state.lock().unwrap().bound
+    }
+
+    /// Replaces the bound, the Rayon workers waiting for room are woken up if it was raised.
+    pub fn set(&self, bound: usize) {
+        let mut state = self.limit.state.lock().unwrap();
+        let raised = bound > state.bound;
+        state.bound = bound;
+        if raised {
+            self.limit.cond.notify_all();
+        }
+    }
+}
+
+/// Yields the items received and gives their slot back.
+pub(crate) struct Tunable<T> {
+    recv: IntoIter<T>,
+    limit: Arc<Limit>,
+}
+
+impl<T> Tunable<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        let x = self.recv.recv(block)?;
+        self.limit.release();
+        Ok(x)
+    }
+}
+
+impl<T> Drop for Tunable<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        self.limit.close();
+    }
+}
+
+/// The number of items in flight and how many are allowed at the same time.
+struct Limit {
+    state: Mutex<LimitState>,
+    /// Notified when an item is consumed, the bound is raised or the consumer is dropped.
+    cond: Condvar,
+}
+
+struct LimitState {
+    in_flight: usize,
+    bound: usize,
+    closed: bool,
+}
+
+impl Limit {
+    fn new(bound: usize) -> Limit {
+        Limit {
+            state: Mutex::new(LimitState { in_flight: 0, bound, closed: false }),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Blocks until there is room for an item, returns `None` if the consumer was dropped.
+    fn acquire(&self) -> Option<()> {
+        let mut state = self.state.lock().unwrap();
+        while state.in_flight >= state.bound && !state.closed {
+            state = self.cond.wait(state).unwrap();
+        }
+
+        if state.closed {
+            return None;
+        }
+
+        state.in_flight += 1;
+        Some(())
+    }
+
+    fn release(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.cond.notify_one();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.cond.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    /// Waits until `cond` holds for the items in flight.
+    fn wait_in_flight(handle: &BoundHandle, cond: impl Fn(usize) -> bool) {
+        let start = Instant::now();
+        while !cond(handle.limit.state.lock().unwrap().in_flight) {
+            assert!(start.elapsed() < Duration::from_secs(30), "the items in flight never settled");
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn raising_the_bound_unblocks_the_producer() {
+        let handle = Cell::new(None);
+        let count = par_bridge_tunable(
+            1,
+            (0u32..1000).into_par_iter(),
+            |bound| handle.set(Some(bound)),
+            |seq_iter| {
+                let handle = handle.take().unwrap();
+                wait_in_flight(&handle, |in_flight| in_flight == 1);
+                // Nothing is consumed, only the new room lets the stalled workers send more.
+                handle.set(8);
+                wait_in_flight(&handle, |in_flight| in_flight == 8);
+                seq_iter.count()
+            },
+        );
+
+        assert_eq!(count, 1000);
+    }
+
+    #[test]
+    fn lowering_the_bound_reduces_the_items_in_flight() {
+        let handle = Cell::new(None);
+        let count = par_bridge_tunable(
+            64,
+            (0u32..1000).into_par_iter(),
+            |bound| handle.set(Some(bound)),
+            |mut seq_iter| {
+                let handle = handle.take().unwrap();
+                wait_in_flight(&handle, |in_flight| in_flight == 64);
+                handle.set(4);
+                assert_eq!(handle.get(), 4);
+
+                // The items already in flight are kept, then the new bound holds.
+                let mut count = seq_iter.by_ref().take(61).count();
+                for _ in seq_iter {
+                    assert!(handle.limit.state.lock().unwrap().in_flight <= 4);
+                    count += 1;
+                }
+                count
+            },
+        );
+
+        assert_eq!(count, 1000);
+    }
+}
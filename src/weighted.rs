@@ -0,0 +1,180 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::channel::{self, IntoIter, TryRecvError};
+use crate::{bridge_with, RayonIntoIter};
+
+/// Same as [`par_bridge`](crate::par_bridge) but bounds the channel by the total weight of the
+/// items waiting to be consumed rather than by their number.
+///
+/// `weight` is called on the Rayon workers and returns the approximate cost of an item, usually
+/// its size in bytes. A worker waits before sending an item when the weight of the items in
+/// flight would exceed `max_bytes`, except when the channel is empty: an item heavier than
+/// `max_bytes` is sent alone instead of blocking the pipeline forever.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_par_bridge::par_bridge_weighted;
+/// use rayon::prelude::*;
+///
+/// let data = (0usize..100).map(|len| "a".repeat(len)).collect::<Vec<_>>();
+///
+/// let total = par_bridge_weighted(1024, String::len, data.into_par_iter(), |seq_iter| {
+///     seq_iter.map(|s| s.len()).sum::<usize>()
+/// });
+/// assert_eq!(total, 4950);
+/// ```
+pub fn par_bridge_weighted<I, F, R, W>(max_bytes: usize, weight: W, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    W: Fn(&I::Item) -> usize + Sync + Send,
+{
+    bridge_weighted(Arc::new(Budget::new(max_bytes)), weight, iter, f)
+}
+
+fn bridge_weighted<I, F, R, W>(budget: Arc<Budget>, weight: W, iter: I, f: F) -> R
+where
+    I: IntoParallelIterator + Send,
+    F: FnOnce(RayonIntoIter<I::Item>) -> R,
+    W: Fn(&I::Item) -> usize + Sync + Send,
+{
+    let producer_budget = budget.clone();
+    bridge_with(
+        thread::Builder::new(),
+        channel::unbounded(),
+        move |send| {
+            iter.into_par_iter().try_for_each(|x| {
+                let weight = weight(&x);
+                producer_budget.acquire(weight)?;
+                send.send((weight, x)).ok()
+            });
+        },
+        |recv| f(RayonIntoIter::weighted(Weighted { recv, budget })),
+    )
+    .expect("failed to spawn the producer thread")
+}
+
+/// Yields the items received and gives their weight back to the budget.
+pub(crate) struct Weighted<T> {
+    recv: IntoIter<(usize, T)>,
+    budget: Arc<Budget>,
+}
+
+impl<T> Weighted<T> {
+    pub(crate) fn producer_finished(&self) -> bool {
+        self.recv.is_disconnected()
+    }
+
+    pub(crate) fn recv(&mut self, block: bool) -> Result<T, TryRecvError> {
+        let (weight, x) = self.recv.recv(block)?;
+        self.budget.release(weight);
+        Ok(x)
+    }
+}
+
+impl<T> Drop for Weighted<T> {
+    fn drop(&mut self) {
+        // Unblock the producers waiting for us, their next send will fail.
+        self.budget.close();
+    }
+}
+
+/// The total weight of the items that can be in flight at the same time.
+struct Budget {
+    max: usize,
+    state: Mutex<BudgetState>,
+    /// Notified when an item is consumed or the consumer is dropped.
+    cond: Condvar,
+}
+
+struct BudgetState {
+    in_flight: usize,
+    closed: bool,
+}
+
+impl Budget {
+    fn new(max: usize) -> Budget {
+        Budget {
+            max,
+            state: Mutex::new(BudgetState { in_flight: 0, closed: false }),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Blocks until an item of this weight fits in the budget,
+    /// returns `None` if the consumer was dropped.
+    fn acquire(&self, weight: usize) -> Option<()> {
+        let mut state = self.state.lock().unwrap();
+        while state.in_flight != 0
+            && state.in_flight.saturating_add(weight) > self.max
+            && !state.closed
+        {
+            state = self.cond.wait(state).unwrap();
+        }
+
+        if state.closed {
+            return None;
+        }
+
+        state.in_flight += weight;
+        Some(())
+    }
+
+    fn release(&self, weight: usize) {
+        self.state.lock().unwrap().in_flight -= weight;
+        self.cond.notify_all();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.cond.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn in_flight_weight_stays_bounded() {
+        const MAX_BYTES: usize = 100_000;
+        const LARGE: usize = 30_000;
+
+        let data = (0..500).map(|i| if i % 2 == 0 { 10 } else { LARGE }).collect::<Vec<_>>();
+        let budget = Arc::new(Budget::new(MAX_BYTES));
+        let parallel_pipeline = data.into_par_iter().map(|len| vec![0u8; len]);
+
+        let (count, peak) =
+            bridge_weighted(budget.clone(), Vec::len, parallel_pipeline, |seq_iter| {
+                let mut peak = 0;
+                let count = seq_iter
+                    .inspect(|_| {
+                        thread::sleep(Duration::from_micros(100));
+                        peak = peak.max(budget.state.lock().unwrap().in_flight);
+                    })
+                    .count();
+                (count, peak)
+            });
+
+        assert_eq!(count, 500);
+        assert!(peak <= MAX_BYTES + LARGE, "{peak} bytes were in flight");
+        assert!(peak > LARGE, "the budget was never used");
+    }
+
+    #[test]
+    fn heavier_than_the_budget() {
+        let data = vec![1000usize; 10];
+        let parallel_pipeline = data.into_par_iter().map(|len| vec![0u8; len]);
+        let count = par_bridge_weighted(10, Vec::len, parallel_pipeline, Iterator::count);
+
+        assert_eq!(count, 10);
+    }
+}
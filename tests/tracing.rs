@@ -0,0 +1,22 @@
+//! The spans of the `tracing` feature, in their own test binary as the subscriber of
+//! `tracing_test` is global and would record the items sent by every other test.
+
+use rayon::prelude::*;
+use rayon_par_bridge::par_bridge;
+use tracing_test::traced_test;
+
+#[test]
+#[traced_test]
+fn producer_and_consumer_spans() {
+    let data = (0u32..100).collect::<Vec<_>>();
+    let count = par_bridge(5, data.par_iter(), |seq_iter| {
+        tracing::info!("consuming");
+        seq_iter.count()
+    });
+    assert_eq!(count, 100);
+
+    // The lines are scoped to the span of the test, the producer span is a child of it even if
+    // it runs on another thread, and the items are sent from the Rayon workers.
+    assert!(logs_contain("par_bridge.producer{bound=5 len=100}: rayon_par_bridge: item sent"));
+    assert!(logs_contain("par_bridge.consumer{bound=5}: tracing: consuming"));
+}